@@ -7,6 +7,8 @@ pub fn routes(cfg: &mut web::ServiceConfig) {
         .route("/", web::get().to(index))
         .service(web::scope("/rooms")
             .route("", web::get().to(rooms::get_rooms))
+            .route("/view", web::get().to(rooms::get_room))
             .route("/create", web::post().to(rooms::post_create_room))
+            .route("/step", web::post().to(rooms::post_step))
         );
 }
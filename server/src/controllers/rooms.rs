@@ -1,15 +1,21 @@
-use actix_web::{HttpResponse, HttpRequest, web::{self, Json}};
+use std::collections::HashMap;
+
+use actix_web::{HttpResponse, web::{self, Json, Query}};
 use serde::{Serialize, Deserialize};
 use serde_json::json;
+use connect_5_rs::{DarkStepOutcome, Point};
 use crate::models::{
     AppData,
     ApiResult,
+    AppResult,
     Room,
+    RoomView,
     User,
     UserId,
     RoomSettings,
     RoomRole,
     RoomUserKey,
+    StateError,
 };
 
 #[derive(Serialize, Deserialize)]
@@ -30,7 +36,6 @@ pub async fn post_create_room(
     params: Json<PostCreateRoomParams>,
     data: AppData,
 ) -> ApiResult {
-    let mut state = data.lock()?;
     let params = params.into_inner();
 
     println!("{}", serde_json::to_string_pretty(&params).unwrap());
@@ -50,7 +55,10 @@ pub async fn post_create_room(
     room.add_user(key, user)?;
 
     // Try adding room, potential duplicate id
-    state.add_room(room)?;
+    data.lock()?.add_room(room)?;
+
+    // The engine takes the first step if it plays black
+    play_ai(&data, &room_id).await?;
 
     Ok(HttpResponse::Ok().json(PostCreateRoomResponse {
         user_id,
@@ -61,9 +69,101 @@ pub async fn post_create_room(
 pub async fn get_rooms(data: AppData) -> ApiResult {
     let rooms = &data.lock()?.rooms;
 
+    // Rooms are listed as seen by anyone who is not in them, so ongoing dark games have no game
+    let views: HashMap<&String, _> = rooms.iter()
+        .map(|(id, room)| (id, room.view(None)))
+        .collect();
+
     let rooms_json = json!({
-        "rooms": rooms,
+        "rooms": views,
     });
 
     Ok(HttpResponse::Ok().json(rooms_json))
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct GetRoomParams {
+    room_id: String,
+    user_id: Option<UserId>,
+}
+
+/// Get a room as seen by the user, which in a dark game hides the pieces the user may not see.
+pub async fn get_room(
+    params: Query<GetRoomParams>,
+    data: AppData,
+) -> ApiResult {
+    let state = data.lock()?;
+    let params = params.into_inner();
+
+    let room = state.rooms.get(&params.room_id)
+        .ok_or_else(|| StateError::new("room_not_found"))?;
+
+    Ok(HttpResponse::Ok().json(room.view(params.user_id.as_ref())))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PostStepParams {
+    room_id: String,
+    user_id: UserId,
+    x: usize,
+    y: usize,
+    /// The layer of the step on a cubic board, 0 otherwise.
+    #[serde(default)]
+    z: usize,
+}
+
+#[derive(Serialize)]
+struct PostStepResponse<'a> {
+    /// `"placed"` if the step was added, or `"hidden"` if the point holds a piece hidden
+    /// from the player, which is now revealed in the view and the player tries again.
+    outcome: &'static str,
+    #[serde(flatten)]
+    room: RoomView<'a>,
+}
+
+pub async fn post_step(
+    params: Json<PostStepParams>,
+    data: AppData,
+) -> ApiResult {
+    let params = params.into_inner();
+
+    let outcome = {
+        let mut state = data.lock()?;
+        let room = state.rooms.get_mut(&params.room_id)
+            .ok_or_else(|| StateError::new("room_not_found"))?;
+
+        match room.add_step(&params.user_id, Point::with_z(params.x, params.y, params.z))? {
+            DarkStepOutcome::Placed => "placed",
+            DarkStepOutcome::Hidden(_) => "hidden",
+        }
+    };
+
+    // Then the reply of the engine, if it has a seat
+    play_ai(&data, &params.room_id).await?;
+
+    let state = data.lock()?;
+    let room = state.rooms.get(&params.room_id)
+        .ok_or_else(|| StateError::new("room_not_found"))?;
+
+    Ok(HttpResponse::Ok().json(PostStepResponse {
+        outcome,
+        room: room.view(Some(&params.user_id)),
+    }))
+}
+
+/// Add the steps of the engine in the room with id, if it is the side to move.
+/// The engine searches without holding the state, so other rooms are not kept waiting.
+async fn play_ai(data: &AppData, room_id: &str) -> AppResult<()> {
+    let turn = data.lock()?.rooms.get(room_id).and_then(Room::ai_turn);
+
+    if let Some((ai, game)) = turn {
+        let step_count = game.step_count();
+        let steps = web::block(move || Ok::<_, ()>(ai.reply(&game))).await?;
+
+        if let Some(room) = data.lock()?.rooms.get_mut(room_id) {
+            room.add_ai_steps(step_count, &steps);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use serde::{Serialize, Deserialize, Serializer, Deserializer, de::Error};
+use connect_5_rs::{Game, GameSide, GameState, Point, Rng};
+use connect_5_rs::engine::{AlphaBeta, Difficulty, Engine, SearchLimits};
+
+/// The longest the engine searches for a single step.
+static SEARCH_TIME: Duration = Duration::from_secs(2);
+
+/// A seat in a room taken by the engine, which plays one side at a difficulty.
+///
+/// ## Serialized Seat
+///
+/// ### Fields
+/// - `side`: `0` for `GameSide::Black` or `1` for `GameSide::White`
+/// - `difficulty`: string, one of `"beginner"`, `"easy"`, `"medium"`, `"hard"` or `"master"`
+#[derive(Debug, Clone, Copy)]
+pub struct AiSeat {
+    pub side: GameSide,
+    pub difficulty: Difficulty,
+}
+
+impl AiSeat {
+    /// Return true if the engine is the side to move in game, otherwise false.
+    pub fn is_to_move(&self, game: &Game) -> bool {
+        matches!(game.state(), GameState::Normal) && game.side() == self.side
+    }
+
+    /// Get the steps the engine plays in game, for as long as it is the side to move.
+    /// The game itself is not changed, so the search can run on a copy of it.
+    pub fn reply(&self, game: &Game) -> Vec<Point> {
+        let mut engine = AlphaBeta::new();
+        engine.set_difficulty(self.difficulty);
+        engine.set_seed(Rng::from_time().next_u64());
+
+        let limits = SearchLimits {
+            time: Some(SEARCH_TIME),
+            ..SearchLimits::default()
+        };
+        let mut game = game.clone();
+        let mut steps = vec![];
+
+        while self.is_to_move(&game) {
+            let point = match engine.search(&game, &limits) {
+                Some(result) => result.best_step,
+                None => break,
+            };
+            if game.add_step(point).is_err() {
+                break;
+            }
+            steps.push(point);
+        }
+
+        steps
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct AiSeatSerializer {
+    side: u16,
+    difficulty: String,
+}
+
+impl Serialize for AiSeat {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer
+    {
+        AiSeatSerializer {
+            side: self.side.index() as u16,
+            difficulty: String::from(self.difficulty.name()),
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for AiSeat {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        let seat = AiSeatSerializer::deserialize(deserializer)?;
+        let side = match seat.side {
+            0 => GameSide::Black,
+            1 => GameSide::White,
+            _ => return Err(Error::custom("expecting side 0 or 1")),
+        };
+        let difficulty = Difficulty::from_name(&seat.difficulty)
+            .ok_or_else(|| Error::custom("expecting a known difficulty"))?;
+
+        Ok(AiSeat {
+            side,
+            difficulty,
+        })
+    }
+}
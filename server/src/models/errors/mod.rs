@@ -7,7 +7,7 @@ pub use state_error::*;
 use std::fmt;
 use std::sync;
 
-use actix_web::{HttpResponse, ResponseError, http::StatusCode};
+use actix_web::{HttpResponse, ResponseError, error::BlockingError, http::StatusCode};
 use serde::Serialize;
 use serde_json::{Value, json};
 
@@ -74,3 +74,9 @@ impl<Guard> From<sync::PoisonError<Guard>> for AppError {
         AppError::internal()
     }
 }
+
+impl<E: fmt::Debug> From<BlockingError<E>> for AppError {
+    fn from(_: BlockingError<E>) -> AppError {
+        AppError::internal()
+    }
+}
@@ -1,7 +1,7 @@
 use std::fmt;
 
 use serde::{Serialize, Deserialize, Serializer, Deserializer, de::Error};
-use connect_5_rs::{Game, Point, GameState, GameSide, GameStepError};
+use connect_5_rs::{BoardGeometry, DarkView, Game, GameRules, Point, GameState, GameSide, GameStepError, RuleSet};
 
 /// Serialize a game object.
 ///
@@ -9,19 +9,37 @@ use connect_5_rs::{Game, Point, GameState, GameSide, GameStepError};
 ///
 /// ### Fields
 /// - `size`: integer representing value of `game.size()`
-/// - `steps`: list of lists `[x, y]`, representing points in `game.iter_steps()`
+/// - `turns`: list of turns from `game.turns()`, each a list of points representing
+///   the points placed in the turn. A point is a list `[x, y]`, or `[x, y, z]` on a cubic board
 /// - `state`: serialized state object (see below), representing value of `game.state()`
+/// - `early_draw`: boolean representing value of `game.rules().early_draw`, false if missing
+/// - `misere`: boolean, true if `game.rules().rule_set` is `RuleSet::Misere`, false if missing
+/// - `players`: integer representing value of `game.players()`, 2 if missing. The players
+///   take turns in the order of their numbers
+/// - `connect`: integer representing value of `game.rules().connect`, 5 if missing
+/// - `first_turn_stones`: integer representing value of `game.rules().first_turn_stones`,
+///   1 if missing
+/// - `stones_per_turn`: integer representing value of `game.rules().stones_per_turn`, 1 if missing
+/// - `geometry`: string, either `"square"`, `"hex"` or `"cubic"`, representing value of
+///   `game.geometry()`, `"square"` if missing
+/// - `obstacles`: list of points as in `turns`, representing `game.obstacles()`, empty if missing
 ///
 /// ## Serialized State
 ///
 /// ### Fields
-/// - `state`: string, either `"normal"`, `"board_full"`, or `"finished"`, depending on state
+/// - `state`: string, either `"normal"`, `"board_full"`, `"no_win_possible"`, or `"finished"`,
+///   depending on state
 ///
 /// ### Optional Fields
 ///
 /// These fields are non-null when `state` is `"finished"`, otherwise null:
-/// - `points`: list with 5 lists `[x, y]`, representing `points`
-/// - `side`: `0` for `GameSide::Black` or `1` for `GameSide::White`, representing `winner_side`
+/// - `lines`: list of winning lines, each a list of points as in `turns`, representing `lines`
+/// - `side`: `0` for `GameSide::Black`, `1` for `GameSide::White`, or the number of a further player,
+///   representing `winner_side`
+///
+/// This field is non-null when `state` is `"finished"` and the game is played under misère rules,
+/// otherwise null:
+/// - `loser`: number of a side as in `side`, representing `loser_side`
 pub fn serialize_game<S>(game: &Game, s: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer
@@ -41,39 +59,67 @@ where
         .map_err(Error::custom)
 }
 
+/// Serialize a point as `[x, y]`, or `[x, y, z]` on a cubic board.
+fn serialize_point(point: &Point, cubic: bool) -> Vec<u32> {
+    if cubic {
+        vec![point.x as u32, point.y as u32, point.z as u32]
+    } else {
+        vec![point.x as u32, point.y as u32]
+    }
+}
+
+/// Deserialize a point from `[x, y]`, or `[x, y, z]` on a cubic board.
+fn deserialize_point(coords: &[u32], cubic: bool) -> Option<Point> {
+    match *coords {
+        [x, y] if !cubic => Some(Point::new(x as usize, y as usize)),
+        [x, y, z] if cubic => Some(Point::with_z(x as usize, y as usize, z as usize)),
+        _ => None,
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct GameStateSerializer {
     state: String,
     side: Option<u16>,
-    points: Option<Vec<(u32, u32)>>,
+    #[serde(default)]
+    loser: Option<u16>,
+    lines: Option<Vec<Vec<Vec<u32>>>>,
 }
 
 impl GameStateSerializer {
-    fn from_state(state: &GameState) -> GameStateSerializer {
+    fn from_state(state: &GameState, cubic: bool) -> GameStateSerializer {
         match state {
             GameState::Normal => {
                 GameStateSerializer {
                     state: String::from("normal"),
                     side: None,
-                    points: None,
+                    loser: None,
+                    lines: None,
                 }
             },
             GameState::BoardFull => {
                 GameStateSerializer {
                     state: String::from("board_full"),
                     side: None,
-                    points: None,
+                    loser: None,
+                    lines: None,
+                }
+            },
+            GameState::NoWinPossible => {
+                GameStateSerializer {
+                    state: String::from("no_win_possible"),
+                    side: None,
+                    loser: None,
+                    lines: None,
                 }
             },
-            GameState::Finished { winner_side, points } => {
+            GameState::Finished { winner_side, loser_side, lines } => {
                 GameStateSerializer {
                     state: String::from("finished"),
-                    side: Some(match winner_side {
-                        GameSide::Black => 0,
-                        GameSide::White => 1,
-                    }),
-                    points: Some(points.iter()
-                        .map(|p| (p.x as u32, p.y as u32))
+                    side: Some(winner_side.index() as u16),
+                    loser: loser_side.map(|side| side.index() as u16),
+                    lines: Some(lines.iter()
+                        .map(|line| line.iter().map(|p| serialize_point(p, cubic)).collect())
                         .collect()
                     ),
                 }
@@ -81,23 +127,25 @@ impl GameStateSerializer {
         }
     }
 
-    fn to_state(&self) -> Option<GameState> {
+    fn to_state(&self, cubic: bool) -> Option<GameState> {
         match &self.state[..] {
             "normal" => Some(GameState::Normal),
             "board_full" => Some(GameState::BoardFull),
+            "no_win_possible" => Some(GameState::NoWinPossible),
             "finished" => {
-                let winner_side = match self.side? {
-                    0 => Some(GameSide::Black),
-                    1 => Some(GameSide::White),
-                    _ => None,
-                }?;
-                let points = self.points.as_deref()?.iter()
-                    .map(|(x, y)| Point::new(*x as usize, *y as usize))
-                    .collect();
+                let winner_side = GameSide::from_index(self.side? as usize)?;
+                let loser_side = match self.loser {
+                    Some(loser) => Some(GameSide::from_index(loser as usize)?),
+                    None => None,
+                };
+                let lines = self.lines.as_deref()?.iter()
+                    .map(|line| line.iter().map(|coords| deserialize_point(coords, cubic)).collect())
+                    .collect::<Option<Vec<Vec<Point>>>>()?;
 
                 Some(GameState::Finished {
                     winner_side,
-                    points,
+                    loser_side,
+                    lines,
                 })
             }
             _ => None,
@@ -107,6 +155,9 @@ impl GameStateSerializer {
 
 enum GameSerializeError {
     CannotAddStep(GameStepError),
+    InvalidPlayers,
+    InvalidGeometry,
+    InvalidObstacles,
     InvalidState,
 }
 
@@ -114,9 +165,15 @@ impl fmt::Display for GameSerializeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", match self {
             GameSerializeError::CannotAddStep(err) => match err {
-                GameStepError::InvalidPoint => "invalid point in steps - out of bounds",
-                GameStepError::PointTaken => "invalid point in steps - duplicate points",
+                GameStepError::InvalidPoint => "invalid point in turns - out of bounds or wrong number of coordinates",
+                GameStepError::PointTaken => "invalid point in turns - duplicate points",
+                GameStepError::Blocked => "invalid point in turns - blocked by an obstacle",
+                GameStepError::Forbidden => "invalid point in turns - forbidden by the rules",
+                GameStepError::InvalidTurn => "invalid turn in turns - wrong number of points",
             },
+            GameSerializeError::InvalidPlayers => "invalid players - at least two are needed",
+            GameSerializeError::InvalidGeometry => "invalid geometry - must be square, hex or cubic",
+            GameSerializeError::InvalidObstacles => "invalid obstacles - out of bounds or duplicate points",
             GameSerializeError::InvalidState => "invalid state - given state does not match derived state",
         })
     }
@@ -125,60 +182,171 @@ impl fmt::Display for GameSerializeError {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GameSerializer {
     size: u32,
-    steps: Vec<(u32, u32)>,
+    turns: Vec<Vec<Vec<u32>>>,
     state: GameStateSerializer,
+    #[serde(default)]
+    early_draw: bool,
+    #[serde(default)]
+    misere: bool,
+    #[serde(default = "GameSerializer::default_players")]
+    players: u32,
+    #[serde(default = "GameSerializer::default_connect")]
+    connect: u32,
+    #[serde(default = "GameSerializer::default_stones")]
+    first_turn_stones: u32,
+    #[serde(default = "GameSerializer::default_stones")]
+    stones_per_turn: u32,
+    #[serde(default = "GameSerializer::default_geometry")]
+    geometry: String,
+    #[serde(default)]
+    obstacles: Vec<Vec<u32>>,
 }
 
 impl GameSerializer {
-    fn from_game(game: &Game) -> GameSerializer {
+    pub fn from_game(game: &Game) -> GameSerializer {
         let size = game.size() as u32;
-        let steps = game.iter_steps()
-            .map(|(_, p)| (p.x as u32, p.y as u32))
+        let cubic = game.geometry() == BoardGeometry::Cubic;
+        let turns = game.turns().iter()
+            .map(|(_, points)| points.iter().map(|p| serialize_point(p, cubic)).collect())
             .collect();
-        let state = GameStateSerializer::from_state(game.state());
+        let state = GameStateSerializer::from_state(game.state(), cubic);
+        let rules = game.rules();
 
         GameSerializer {
             size,
-            steps,
+            turns,
             state,
+            early_draw: rules.early_draw,
+            misere: rules.rule_set == RuleSet::Misere,
+            players: game.players() as u32,
+            connect: rules.connect as u32,
+            first_turn_stones: rules.first_turn_stones as u32,
+            stones_per_turn: rules.stones_per_turn as u32,
+            geometry: String::from(game.geometry().name()),
+            obstacles: game.obstacles().iter().map(|p| serialize_point(p, cubic)).collect(),
         }
     }
 
-    fn to_game(&self) -> Result<Game, GameSerializeError> {
-        let steps = self.steps.iter()
-            .map(|(x, y)| Point::new(*x as usize, *y as usize))
-            .collect::<Vec<Point>>();
+    fn default_players() -> u32 {
+        2
+    }
+
+    fn default_connect() -> u32 {
+        GameRules::default().connect as u32
+    }
 
-        let game = match Game::from_steps(self.size as usize, &steps) {
-            Ok(game) => game,
-            Err(err) => return Err(GameSerializeError::CannotAddStep(err)),
+    fn default_stones() -> u32 {
+        1
+    }
+
+    fn default_geometry() -> String {
+        String::from("square")
+    }
+
+    fn to_game(&self) -> Result<Game, GameSerializeError> {
+        let geometry = match BoardGeometry::from_name(&self.geometry) {
+            Some(geometry) => geometry,
+            None => return Err(GameSerializeError::InvalidGeometry),
+        };
+        let cubic = geometry == BoardGeometry::Cubic;
+        let rules = GameRules {
+            early_draw: self.early_draw,
+            connect: self.connect as usize,
+            first_turn_stones: self.first_turn_stones as usize,
+            stones_per_turn: self.stones_per_turn as usize,
+            geometry,
+            rule_set: if self.misere { RuleSet::Misere } else { RuleSet::Freestyle },
         };
+        let mut game = match Game::with_players(self.size as usize, rules, self.players as usize) {
+            Some(game) => game,
+            None => return Err(GameSerializeError::InvalidPlayers),
+        };
+        for coords in &self.obstacles {
+            let blocked = deserialize_point(coords, cubic).map(|point| game.block(point));
+            if !matches!(blocked, Some(Ok(()))) {
+                return Err(GameSerializeError::InvalidObstacles);
+            }
+        }
+        for (i, turn) in self.turns.iter().enumerate() {
+            let points = match turn.iter().map(|coords| deserialize_point(coords, cubic)).collect::<Option<Vec<Point>>>() {
+                Some(points) => points,
+                None => return Err(GameSerializeError::CannotAddStep(GameStepError::InvalidPoint)),
+            };
+
+            // Only the last turn may be partly taken, as the game is still waiting for its stones
+            let partial = !points.is_empty() && points.len() < game.stones_left();
+            let result = if i + 1 == self.turns.len() && partial {
+                points.iter().try_for_each(|point| game.add_step(*point))
+            } else {
+                game.add_turn(&points)
+            };
+
+            if let Err(err) = result {
+                return Err(GameSerializeError::CannotAddStep(err));
+            }
+        }
 
         // Verify that the given state is same as state derived from steps
-        let given_state = match self.state.to_state() {
+        let given_state = match self.state.to_state(cubic) {
             Some(state) => state,
             None => return Err(GameSerializeError::InvalidState),
         };
         let derived_state = game.state();
 
-        match (&given_state, derived_state) {
-            (GameState::Normal, GameState::Normal) |
-            (GameState::BoardFull, GameState::BoardFull) => (),
-            // Possible to have different winning points (e.g. when connect 6 in a row)
-            // However, winning side must be the same
-            (
-                GameState::Finished {
-                    winner_side: side1,
-                    points: _
-                },
-                GameState::Finished {
-                    winner_side: side2,
-                    points: _
-                },
-            ) if side1 == side2 => (),
-            _ => return Err(GameSerializeError::InvalidState),
-        };
+        // Winning lines are ordered the same way however the game was played,
+        // so the states must match exactly
+        if given_state != *derived_state {
+            return Err(GameSerializeError::InvalidState);
+        }
 
         Ok(game)
     }
 }
+
+/// What a side sees of a dark game, serialized.
+///
+/// ## Serialized View
+///
+/// ### Fields
+/// - `size`: integer representing value of `game.size()`
+/// - `side`: number of a side as in the serialized state, representing `view.side`
+/// - `next_side`: number of a side as in `side`, representing `view.next_side`
+/// - `step_count`: integer representing `view.step_count`, counting the hidden pieces
+/// - `pieces`: list of the visible pieces from `view.pieces`, each an object with the number `side`
+///   of its side and its `point`, a list `[x, y]`, or `[x, y, z]` on a cubic board
+/// - `obstacles`: list of points as in `pieces`, representing `game.obstacles()`
+#[derive(Serialize, Debug)]
+pub struct DarkViewSerializer {
+    size: u32,
+    side: u16,
+    next_side: u16,
+    step_count: u32,
+    pieces: Vec<PieceSerializer>,
+    obstacles: Vec<Vec<u32>>,
+}
+
+#[derive(Serialize, Debug)]
+struct PieceSerializer {
+    side: u16,
+    point: Vec<u32>,
+}
+
+impl DarkViewSerializer {
+    pub fn from_view(view: &DarkView, game: &Game) -> DarkViewSerializer {
+        let cubic = game.geometry() == BoardGeometry::Cubic;
+
+        DarkViewSerializer {
+            size: game.size() as u32,
+            side: view.side.index() as u16,
+            next_side: view.next_side.index() as u16,
+            step_count: view.step_count as u32,
+            pieces: view.pieces.iter()
+                .map(|(side, point)| PieceSerializer {
+                    side: side.index() as u16,
+                    point: serialize_point(point, cubic),
+                })
+                .collect(),
+            obstacles: game.obstacles().iter().map(|p| serialize_point(p, cubic)).collect(),
+        }
+    }
+}
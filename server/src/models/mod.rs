@@ -4,6 +4,7 @@ mod user;
 mod game_serde;
 mod role;
 mod errors;
+mod ai;
 
 use std::sync::Mutex;
 use actix_web::{HttpResponse, web::Data};
@@ -13,6 +14,7 @@ pub use room::*;
 pub use user::*;
 pub use role::*;
 pub use errors::*;
+pub use ai::*;
 
 /// Web data, representing state of the app.
 pub type AppData = Data<Mutex<AppState>>;
@@ -4,6 +4,9 @@ use serde::{Serialize, Deserialize, Serializer, Deserializer, de::{Visitor, Erro
 use connect_5_rs::GameSide;
 
 /// A role that a user has in a room.
+///
+/// Serialized as `0` for black, `1` for white, `2` for a spectator,
+/// and the number of the side plus one for further players, such as `3` for the third player.
 #[derive(Debug)]
 pub enum RoomRole {
     Player(GameSide),
@@ -19,6 +22,7 @@ impl Serialize for RoomRole {
             RoomRole::Player(GameSide::Black) => 0,
             RoomRole::Player(GameSide::White) => 1,
             RoomRole::Spectator => 2,
+            RoomRole::Player(side) => side.index() as u16 + 1,
         })
     }
 }
@@ -38,7 +42,7 @@ impl<'de> Visitor<'de> for RoomRoleVisitor {
     type Value = RoomRole;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "an integer between 0 and 2")
+        write!(f, "a non-negative integer")
     }
 
     fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
@@ -49,7 +53,9 @@ impl<'de> Visitor<'de> for RoomRoleVisitor {
             0 => Ok(RoomRole::Player(GameSide::Black)),
             1 => Ok(RoomRole::Player(GameSide::White)),
             2 => Ok(RoomRole::Spectator),
-            _ => Err(Error::custom("expecting integer between 0 and 2")),
+            _ => GameSide::from_index(v as usize - 1)
+                .map(RoomRole::Player)
+                .ok_or_else(|| Error::custom("expecting a side of a player")),
         }
     }
 }
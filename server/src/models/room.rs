@@ -1,19 +1,40 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use serde::{Serialize, Deserialize};
-use connect_5_rs::{Game, GameSide};
+use connect_5_rs::{
+    BoardGeometry,
+    DarkStepOutcome,
+    Fog,
+    Game,
+    GameRules,
+    GameSide,
+    GameState,
+    GameStepError,
+    Point,
+    Rng,
+    RuleSet,
+    random_obstacles,
+};
 use crate::models::{
+    AiSeat,
+    AppError,
+    PublicUser,
+    RoomRole,
     User,
     UserId,
     StateError,
     ValidationError,
-    game_serde::{serialize_game, deserialize_game},
+    game_serde::{DarkViewSerializer, GameSerializer, serialize_game, deserialize_game},
 };
 
 static MAX_ROOM_ID_LENGTH: usize = 15;
 static MIN_BOARD_SIZE: usize = 9;
 static MAX_BOARD_SIZE: usize = 21;
 static DEFAULT_BOARD_SIZE: usize = 15;
+static MIN_PLAYERS: usize = 2;
+static MAX_PLAYERS: usize = 4;
+static DEFAULT_PLAYERS: usize = 2;
+static DEFAULT_SPECTATOR_DELAY: usize = 6;
 
 /// The settings for a Room.
 #[derive(Serialize, Deserialize, Debug)]
@@ -26,6 +47,46 @@ pub struct RoomSettings {
 
     #[serde(default = "RoomSettings::default_public")]
     pub public: bool,
+
+    /// The number of player seats, which take turns in order starting with black.
+    #[serde(default = "RoomSettings::default_players")]
+    pub players: usize,
+
+    /// Whether the game is played under Connect6 rules, where after the first stone
+    /// each turn has two stones and six in a row wins.
+    #[serde(default)]
+    pub connect6: bool,
+
+    /// Whether the game is played under misère rules, where the side that makes a row loses.
+    #[serde(default)]
+    pub misere: bool,
+
+    /// Whether the game is dark, where each player only sees their own pieces and those
+    /// of other players that they tried to play on.
+    #[serde(default)]
+    pub dark: bool,
+
+    /// The number of the latest steps hidden from spectators of a dark game, until it is over.
+    #[serde(default = "RoomSettings::default_spectator_delay")]
+    pub spectator_delay: usize,
+
+    /// The name of the geometry of the board: `"square"`, `"hex"` with lines in three directions,
+    /// or `"cubic"` with board_size layers and lines in 13 directions.
+    #[serde(default = "RoomSettings::default_geometry")]
+    pub geometry: String,
+
+    /// Points blocked on the board before the game starts, which neither side may use.
+    /// Each point is `[x, y]`, or `[x, y, z]` on a cubic board.
+    #[serde(default)]
+    pub obstacles: Vec<Vec<usize>>,
+
+    /// Points blocked at random, after the chosen obstacles, if any.
+    #[serde(default)]
+    pub random_obstacles: Option<RandomObstacles>,
+
+    /// The seat taken by the engine, if any.
+    #[serde(default)]
+    pub ai: Option<AiSeat>,
 }
 
 impl RoomSettings {
@@ -40,6 +101,29 @@ impl RoomSettings {
     fn default_public() -> bool {
         true
     }
+
+    fn default_players() -> usize {
+        DEFAULT_PLAYERS
+    }
+
+    fn default_spectator_delay() -> usize {
+        DEFAULT_SPECTATOR_DELAY
+    }
+
+    fn default_geometry() -> String {
+        String::from(BoardGeometry::Square.name())
+    }
+
+    /// Get the points of the chosen obstacles, leaving out any with the wrong number of coordinates.
+    fn obstacle_points(&self) -> Vec<Point> {
+        self.obstacles.iter()
+            .filter_map(|coords| match coords[..] {
+                [x, y] => Some(Point::new(x, y)),
+                [x, y, z] => Some(Point::with_z(x, y, z)),
+                _ => None,
+            })
+            .collect()
+    }
 }
 
 impl Default for RoomSettings {
@@ -48,59 +132,163 @@ impl Default for RoomSettings {
             board_size: RoomSettings::default_board_size(),
             allow_spectators: RoomSettings::default_allow_spectators(),
             public: RoomSettings::default_public(),
+            players: RoomSettings::default_players(),
+            connect6: false,
+            misere: false,
+            dark: false,
+            spectator_delay: RoomSettings::default_spectator_delay(),
+            geometry: RoomSettings::default_geometry(),
+            obstacles: vec![],
+            random_obstacles: None,
+            ai: None,
         }
     }
 }
 
+/// Obstacles placed at random points of the board, chosen from a seed
+/// so the same settings always block the same points.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RandomObstacles {
+    pub count: usize,
+    pub seed: u64,
+}
+
 /// A game room where two players can play a game.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Room {
     pub id: String,
     pub settings: RoomSettings,
-    pub player1: Option<User>,
-    pub player2: Option<User>,
+    /// The player seats, in turn order. A seat is `None` until a user takes it.
+    pub players: Vec<Option<User>>,
     pub spectators: HashMap<UserId, User>,
     #[serde(serialize_with = "serialize_game", deserialize_with = "deserialize_game")]
     pub game: Game,
+    /// The pieces revealed to each player of a dark game, which are never sent as they are.
+    #[serde(skip)]
+    pub fog: Fog,
+}
+
+/// A room as seen by one user, which is what users are sent instead of the room.
+///
+/// In a dark game that is not over, a player only gets their own view of the game,
+/// a spectator of the room gets the game without its latest steps,
+/// and anyone else gets no game.
+#[derive(Serialize, Debug)]
+pub struct RoomView<'a> {
+    pub id: &'a str,
+    pub settings: &'a RoomSettings,
+    pub players: Vec<Option<PublicUser<'a>>>,
+    pub spectators: Vec<PublicUser<'a>>,
+    pub game: Option<GameSerializer>,
+    pub view: Option<DarkViewSerializer>,
 }
 
 impl Room {
     /// Create a new room with default values.
+    /// The game ends in a draw as soon as neither side can win.
     fn new(id: String, settings: RoomSettings) -> Room {
-        let game = Game::new(settings.board_size);
+        let mut rules = GameRules {
+            early_draw: true,
+            geometry: BoardGeometry::from_name(&settings.geometry).expect("geometry should be validated"),
+            ..if settings.connect6 { GameRules::connect6() } else { GameRules::default() }
+        };
+        if settings.misere {
+            rules.rule_set = RuleSet::Misere;
+        }
+        let mut game = Game::with_players(settings.board_size, rules, settings.players)
+            .expect("number of players should be validated");
+        for point in settings.obstacle_points() {
+            game.block(point).expect("obstacles should be validated");
+        }
+        if let Some(random) = &settings.random_obstacles {
+            for point in random_obstacles(&game, random.count, &mut Rng::new(random.seed)) {
+                game.block(point).expect("random obstacles should be empty points");
+            }
+        }
+        let players = (0..settings.players).map(|_| None).collect();
         let spectators = HashMap::new();
 
         Room {
             id,
             settings,
-            player1: None,
-            player2: None,
+            players,
             spectators,
             game,
+            fog: Fog::new(),
         }
     }
 
     /// Get the user from room with key, if it exists.
     pub fn get_user(&self, key: &RoomUserKey) -> Option<&User> {
         match key {
-            RoomUserKey::Player(GameSide::Black) => self.player1.as_ref(),
-            RoomUserKey::Player(GameSide::White) => self.player2.as_ref(),
-            RoomUserKey::Spectator(id) => self.spectators.get(&id),
+            RoomUserKey::Player(side) => self.players.get(side.index()).and_then(Option::as_ref),
+            RoomUserKey::Spectator(id) => self.spectators.get(id),
+        }
+    }
+
+    /// Get the side played by the user with id, if the user is a player in the room.
+    pub fn player_side(&self, id: &UserId) -> Option<GameSide> {
+        self.players.iter()
+            .position(|user| user.as_ref().is_some_and(|user| user.id == *id))
+            .and_then(GameSide::from_index)
+    }
+
+    /// Return true if the side is played by the engine, otherwise false.
+    pub fn is_ai_side(&self, side: GameSide) -> bool {
+        self.settings.ai.is_some_and(|ai| ai.side == side)
+    }
+
+    /// Get the room as seen by the user with id, or by anyone who is not in the room if there is no id.
+    pub fn view(&self, id: Option<&UserId>) -> RoomView<'_> {
+        let (game, view) = if !self.settings.dark || !matches!(self.game.state(), GameState::Normal) {
+            (Some(GameSerializer::from_game(&self.game)), None)
+        } else if let Some(side) = id.and_then(|id| self.player_side(id)) {
+            (None, Some(DarkViewSerializer::from_view(&self.fog.view(&self.game, side), &self.game)))
+        } else if !id.is_some_and(|id| self.spectators.contains_key(id)) {
+            (None, None)
+        } else {
+            let mut game = self.game.clone();
+            for _ in 0..self.settings.spectator_delay {
+                game.undo_step();
+            }
+            (Some(GameSerializer::from_game(&game)), None)
+        };
+
+        RoomView {
+            id: &self.id,
+            settings: &self.settings,
+            players: self.players.iter()
+                .enumerate()
+                .map(|(i, user)| {
+                    let side = GameSide::from_index(i)?;
+                    user.as_ref().map(|user| user.public(RoomRole::Player(side)))
+                })
+                .collect(),
+            spectators: self.spectators.values()
+                .map(|user| user.public(RoomRole::Spectator))
+                .collect(),
+            game,
+            view,
         }
     }
 
     /// Add user to room using a key, provided the spot isn't taken.
     pub fn add_user(&mut self, key: RoomUserKey, user: User) -> Result<(), StateError> {
+        let ai_seat = match key {
+            RoomUserKey::Player(side) if side.index() >= self.players.len() => {
+                return Err(StateError::new("room_spot_invalid"));
+            },
+            RoomUserKey::Player(side) => self.is_ai_side(side),
+            RoomUserKey::Spectator(_) => false,
+        };
+
         // TODO: use a different type of error here
-        if self.get_user(&key).is_some() {
+        if ai_seat || self.get_user(&key).is_some() {
             Err(StateError::new("room_spot_taken"))
         } else {
             match key {
-                RoomUserKey::Player(GameSide::Black) => {
-                    self.player1 = Some(user);
-                },
-                RoomUserKey::Player(GameSide::White) => {
-                    self.player2 = Some(user);
+                RoomUserKey::Player(side) => {
+                    self.players[side.index()] = Some(user);
                 },
                 RoomUserKey::Spectator(id) => {
                     self.spectators.insert(id, user);
@@ -111,9 +299,61 @@ impl Room {
     }
 }
 
+// Game
+impl Room {
+    /// Add a step for the player with user id.
+    /// In a dark game, a point with a piece hidden from the player reveals it instead,
+    /// and the player keeps the turn.
+    pub fn add_step(&mut self, id: &UserId, point: Point) -> Result<DarkStepOutcome, AppError> {
+        let side = self.player_side(id).ok_or_else(|| StateError::new("not_a_player"))?;
+
+        if !matches!(self.game.state(), GameState::Normal) {
+            return Err(StateError::new("game_over").into());
+        }
+        if self.game.side() != side {
+            return Err(StateError::new("not_your_turn").into());
+        }
+
+        let result = if self.settings.dark {
+            self.fog.add_step(&mut self.game, point)
+        } else {
+            self.game.add_step(point).map(|_| DarkStepOutcome::Placed)
+        };
+        let outcome = result.map_err(|err| ValidationError::new("point", Some(match err {
+            GameStepError::InvalidPoint => "out_of_bounds",
+            GameStepError::PointTaken => "point_taken",
+            GameStepError::Blocked => "blocked",
+            GameStepError::Forbidden => "forbidden",
+            GameStepError::InvalidTurn => "invalid_turn",
+        })))?;
+
+        Ok(outcome)
+    }
+
+    /// Get the engine seat and a copy of the game, if the engine is the side to move.
+    pub fn ai_turn(&self) -> Option<(AiSeat, Game)> {
+        self.settings.ai
+            .filter(|ai| ai.is_to_move(&self.game))
+            .map(|ai| (ai, self.game.clone()))
+    }
+
+    /// Add the steps of the engine, searched on the game when it had step_count steps.
+    /// If the game has changed since, the steps are left out.
+    pub fn add_ai_steps(&mut self, step_count: usize, steps: &[Point]) {
+        if self.game.step_count() != step_count {
+            return;
+        }
+        for point in steps {
+            if self.game.add_step(*point).is_err() {
+                break;
+            }
+        }
+    }
+}
+
 // Validation
 impl Room {
-    fn validate_id(id: &String) -> Result<(), ValidationError> {
+    fn validate_id(id: &str) -> Result<(), ValidationError> {
         if (1..=MAX_ROOM_ID_LENGTH).contains(&id.len()) {
             Ok(())
         } else {
@@ -122,13 +362,48 @@ impl Room {
     }
 
     fn validate_settings(settings: &RoomSettings) -> Result<(), ValidationError> {
-        if (MIN_BOARD_SIZE..=MAX_BOARD_SIZE).contains(&settings.board_size) {
-            Ok(())
-        } else {
+        if !(MIN_BOARD_SIZE..=MAX_BOARD_SIZE).contains(&settings.board_size) {
             Err(ValidationError::new("board_size", None))
+        } else if !(MIN_PLAYERS..=MAX_PLAYERS).contains(&settings.players) {
+            Err(ValidationError::new("players", None))
+        } else if BoardGeometry::from_name(&settings.geometry).is_none() {
+            Err(ValidationError::new("geometry", None))
+        } else if !Room::are_obstacles_valid(settings) {
+            Err(ValidationError::new("obstacles", None))
+        } else if settings.ai.is_some()
+            && (settings.players != 2 || settings.connect6 || settings.geometry == BoardGeometry::Cubic.name())
+        {
+            // The engine only plays games with two players and one stone per turn on a single layer
+            Err(ValidationError::new("ai", None))
+        } else if settings.ai.is_some() && settings.dark {
+            // The engine sees every piece, so it cannot play a dark game fairly
+            Err(ValidationError::new("ai", None))
+        } else {
+            Ok(())
         }
     }
 
+    /// Return true if the chosen obstacles are different points on the board, and all the
+    /// obstacles cover at most half of it, otherwise false.
+    fn are_obstacles_valid(settings: &RoomSettings) -> bool {
+        let size = settings.board_size;
+        let cubic = settings.geometry == BoardGeometry::Cubic.name();
+        let layers = if cubic { size } else { 1 };
+
+        let mut seen = HashSet::new();
+        let chosen = settings.obstacles.iter().all(|coords| {
+            let on_board = match coords[..] {
+                [x, y] => !cubic && x < size && y < size,
+                [x, y, z] => cubic && x < size && y < size && z < size,
+                _ => false,
+            };
+            on_board && seen.insert(coords)
+        });
+        let count = settings.obstacles.len() + settings.random_obstacles.as_ref().map_or(0, |random| random.count);
+
+        chosen && count <= size * size * layers / 2
+    }
+
     /// Create a room with given id and settings. If these are invalid, return the error,
     /// otherwise return the created room.
     pub fn validate(id: String, settings: RoomSettings) -> Result<Room, ValidationError> {
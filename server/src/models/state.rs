@@ -1,21 +1,12 @@
 use std::collections::HashMap;
-use connect_5_rs::{Game, Point, GameSide};
-use crate::models::{StateError, Room, RoomSettings, User, UserId, RoomUserKey};
+use connect_5_rs::{Fog, Game, Point};
+use crate::models::{StateError, Room, RoomSettings, User};
 
 pub struct AppState {
     pub rooms: HashMap<String, Room>,
-    pub users: HashMap<UserId, RoomUserKey>,
 }
 
 impl AppState {
-    /// Create a new app state.
-    pub fn new() -> AppState {
-        AppState {
-            rooms: HashMap::new(),
-            users: HashMap::new(),
-        }
-    }
-
     /// Add a room to a room. If the room with same id already exist, return error.
     pub fn add_room(&mut self, room: Room) -> Result<(), StateError> {
         if self.rooms.contains_key(&room.id) {
@@ -30,14 +21,10 @@ impl AppState {
     /// Testing data.
     pub fn test_data() -> AppState {
         let mut rooms = HashMap::new();
-        let mut users = HashMap::new();
         let mut spectators = HashMap::new();
 
         let player2 = User::new(None);
-        users.insert(player2.id, RoomUserKey::Player(GameSide::White));
-
         let spectator = User::new(Some(String::from("some spectator")));
-        users.insert(spectator.id, RoomUserKey::Spectator(spectator.id));
         spectators.insert(spectator.id, spectator);
 
         rooms.insert(String::from("test id"),
@@ -47,10 +34,18 @@ impl AppState {
                     board_size: 15,
                     allow_spectators: true,
                     public: true,
+                    players: 2,
+                    connect6: false,
+                    misere: false,
+                    dark: false,
+                    spectator_delay: 6,
+                    geometry: String::from("square"),
+                    obstacles: vec![],
+                    random_obstacles: None,
+                    ai: None,
                 },
-                player1: None,
-                player2: Some(player2),
-                game: Game::from_steps(15, &vec![
+                players: vec![None, Some(player2)],
+                game: Game::from_steps(15, &[
                     Point::new(0, 0),
                     Point::new(1, 2),
                     Point::new(1, 1),
@@ -62,11 +57,11 @@ impl AppState {
                     Point::new(4, 4), // game finishes here
                 ]).unwrap(),
                 spectators,
+                fog: Fog::new(),
             }
         );
 
         AppState  {
-            users,
             rooms,
         }
     }
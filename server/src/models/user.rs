@@ -1,4 +1,5 @@
 use serde::{Serialize, Deserialize};
+use crate::models::RoomRole;
 
 pub type UserId = uuid::Uuid;
 
@@ -16,3 +17,21 @@ impl User {
         }
     }
 }
+
+/// A user as seen by the other users of a room, which leaves out the id,
+/// since the id is all the server asks of a user to act as them.
+#[derive(Serialize, Debug)]
+pub struct PublicUser<'a> {
+    pub nickname: Option<&'a str>,
+    pub role: RoomRole,
+}
+
+impl User {
+    /// Get the user as seen by others, with the role the user has in a room.
+    pub fn public(&self, role: RoomRole) -> PublicUser<'_> {
+        PublicUser {
+            nickname: self.nickname.as_deref(),
+            role,
+        }
+    }
+}
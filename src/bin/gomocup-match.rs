@@ -0,0 +1,444 @@
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, ChildStdin, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use connect_5_rs::{Game, GameRecord, GameSide, GameState, MAX_SGF_SIZE, Point, RecordError, format_sgf_points, read_openings};
+use connect_5_rs::protocol::{self, BoardField, Command, Response};
+
+/// The extra time given to a brain on top of its limits, for starting up and communication.
+static TIME_TOLERANCE: Duration = Duration::from_millis(500);
+
+/// The time given to a brain to start up and reply to `START`.
+static START_TIMEOUT: Duration = Duration::from_secs(30);
+
+static USAGE: &str = "\
+Usage: gomocup-match <brain1> <brain2> [options]
+
+Play a match between two Gomocup protocol brains, alternating colors each game.
+
+Options:
+  --games <n>         number of games to play (default 2)
+  --size <n>          board size (default 15)
+  --turn-time <ms>    time limit for each turn (default 5000)
+  --match-time <ms>   time limit for all turns of a brain in a game (default none)
+  --output <dir>      directory for results and game records (default match-output)
+  --openings <file>   openings to start games from, as written by opening-generator.
+                      Each opening is played twice, once with each brain as black.";
+
+/// Settings of a match.
+struct MatchConfig {
+    brains: [PathBuf; 2],
+    games: usize,
+    size: usize,
+    turn_time: Duration,
+    match_time: Option<Duration>,
+    output: PathBuf,
+    openings: Vec<Vec<Point>>,
+}
+
+impl MatchConfig {
+    /// Read the settings from command line arguments.
+    fn from_args(args: &[String]) -> Result<MatchConfig, String> {
+        let mut brains = vec![];
+        let mut config = MatchConfig {
+            brains: [PathBuf::new(), PathBuf::new()],
+            games: 2,
+            size: 15,
+            turn_time: Duration::from_millis(5000),
+            match_time: None,
+            output: PathBuf::from("match-output"),
+            openings: vec![],
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next().ok_or_else(|| format!("missing value for {}", name))
+            };
+            let number = |text: &String| {
+                text.parse::<u64>().map_err(|_| format!("invalid number {}", text))
+            };
+
+            match &arg[..] {
+                "--games" => config.games = number(value(arg)?)? as usize,
+                "--size" => config.size = number(value(arg)?)? as usize,
+                "--turn-time" => config.turn_time = Duration::from_millis(number(value(arg)?)?),
+                "--match-time" => config.match_time = Some(Duration::from_millis(number(value(arg)?)?)),
+                "--output" => config.output = PathBuf::from(value(arg)?),
+                "--openings" => {
+                    let path = value(arg)?;
+                    let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+                    config.openings = read_openings(&text).map_err(|err| format!("{}: {}", path, err))?;
+                },
+                _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+                _ => brains.push(PathBuf::from(arg)),
+            }
+        }
+
+        match &brains[..] {
+            [first, second] => config.brains = [first.clone(), second.clone()],
+            _ => return Err(String::from("expected exactly two brains")),
+        }
+        if !(5..=MAX_SGF_SIZE).contains(&config.size) {
+            return Err(format!("board size must be between 5 and {}", MAX_SGF_SIZE));
+        }
+
+        Ok(config)
+    }
+}
+
+/// The reason a game ended.
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum EndReason {
+    Five,
+    BoardFull,
+    Timeout,
+    IllegalStep,
+    BrainError,
+}
+
+impl EndReason {
+    fn describe(&self) -> &'static str {
+        match self {
+            EndReason::Five => "five in a row",
+            EndReason::BoardFull => "board full",
+            EndReason::Timeout => "timeout",
+            EndReason::IllegalStep => "illegal step",
+            EndReason::BrainError => "brain error",
+        }
+    }
+}
+
+/// A brain running as a child process.
+struct Brain {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    /// The time left for the brain in the current game, if limited.
+    time_left: Option<Duration>,
+}
+
+impl Brain {
+    /// Start the brain at path.
+    fn spawn(path: &Path) -> io::Result<Brain> {
+        let mut child = process::Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        // Read lines on a separate thread, so waiting for a reply can time out
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => if sender.send(line).is_err() {
+                        break;
+                    },
+                    Err(_) => break,
+                }
+            }
+        });
+
+        let name = path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| String::from("brain"));
+
+        Ok(Brain {
+            name,
+            child,
+            stdin,
+            lines,
+            time_left: None,
+        })
+    }
+
+    fn send(&mut self, command: &Command) -> Result<(), EndReason> {
+        writeln!(self.stdin, "{}", command)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|_| EndReason::BrainError)
+    }
+
+    /// Wait for a reply within timeout, skipping messages and debugging output.
+    fn receive(&mut self, timeout: Duration) -> Result<Response, EndReason> {
+        let start = Instant::now();
+
+        loop {
+            let remaining = timeout.checked_sub(start.elapsed()).ok_or(EndReason::Timeout)?;
+            let line = match self.lines.recv_timeout(remaining) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => return Err(EndReason::Timeout),
+                Err(RecvTimeoutError::Disconnected) => return Err(EndReason::BrainError),
+            };
+
+            match Response::parse(&line) {
+                Response::Message(message) => println!("  [{}] {}", self.name, message),
+                Response::Debug(_) => (),
+                response => return Ok(response),
+            }
+        }
+    }
+
+    /// Send a command and wait for the next step of the brain, enforcing the time limits.
+    fn request_step(&mut self, command: &Command, turn_time: Duration) -> Result<Response, EndReason> {
+        if let Some(time_left) = self.time_left {
+            self.send(&Command::Info(String::from("time_left"), time_left.as_millis().to_string()))?;
+        }
+        self.send(command)?;
+
+        let limit = match self.time_left {
+            Some(time_left) => turn_time.min(time_left),
+            None => turn_time,
+        };
+        let start = Instant::now();
+        let response = self.receive(limit + TIME_TOLERANCE)?;
+
+        if let Some(time_left) = &mut self.time_left {
+            *time_left = time_left.checked_sub(start.elapsed()).ok_or(EndReason::Timeout)?;
+        }
+
+        Ok(response)
+    }
+
+    /// Start a new game on the brain, and read its name.
+    fn start(&mut self, config: &MatchConfig) -> Result<(), EndReason> {
+        self.send(&Command::About)?;
+        if let Response::Other(about) = self.receive(START_TIMEOUT)? {
+            if let Some(name) = protocol::about_value(&about, "name") {
+                self.name = name;
+            }
+        }
+
+        self.send(&Command::Info(String::from("timeout_turn"), config.turn_time.as_millis().to_string()))?;
+        if let Some(match_time) = config.match_time {
+            self.send(&Command::Info(String::from("timeout_match"), match_time.as_millis().to_string()))?;
+        }
+        self.time_left = config.match_time;
+
+        self.send(&Command::Start(config.size))?;
+        match self.receive(START_TIMEOUT)? {
+            Response::Ok => Ok(()),
+            _ => Err(EndReason::BrainError),
+        }
+    }
+
+    /// Ask the brain to exit, and make sure it does.
+    fn stop(mut self) {
+        let _ = self.send(&Command::End);
+        thread::sleep(Duration::from_millis(100));
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The outcome of a single game.
+struct GameOutcome {
+    game: Game,
+    winner: Option<GameSide>,
+    reason: EndReason,
+    names: [String; 2],
+}
+
+/// Play a game from the opening, with brains in the order black and white.
+fn play_game(config: &MatchConfig, paths: [&Path; 2], opening: &[Point]) -> io::Result<GameOutcome> {
+    let mut brains = [Brain::spawn(paths[0])?, Brain::spawn(paths[1])?];
+    let mut game = Game::from_steps(config.size, opening)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid opening: {}", err)))?;
+    // Whether each brain has been sent the position, after which it only needs the last step
+    let mut synced = [false, false];
+
+    let mut result = Ok(());
+    for (i, brain) in brains.iter_mut().enumerate() {
+        if let Err(reason) = brain.start(config) {
+            result = Err((i, reason));
+            break;
+        }
+    }
+
+    let mut turn = game.step_count() % 2;
+    while result.is_ok() && matches!(game.state(), GameState::Normal) {
+        let command = match game.last_step() {
+            None => Command::Begin,
+            Some(point) if synced[turn] => Command::Turn(point),
+            Some(_) => Command::Board(board_fields(&game)),
+        };
+        synced[turn] = true;
+
+        let step = match brains[turn].request_step(&command, config.turn_time) {
+            Ok(Response::Step(point)) => game.validate_step(&point).map(|_| point).map_err(|_| EndReason::IllegalStep),
+            Ok(_) => Err(EndReason::BrainError),
+            Err(reason) => Err(reason),
+        };
+
+        match step {
+            Ok(point) => {
+                game.add_step(point).expect("validated step should be added");
+                turn = 1 - turn;
+            },
+            Err(reason) => result = Err((turn, reason)),
+        }
+    }
+
+    let names = [brains[0].name.clone(), brains[1].name.clone()];
+    for brain in brains {
+        brain.stop();
+    }
+
+    let (winner, reason) = match (result, game.state()) {
+        // The brain at fault loses
+        (Err((0, reason)), _) => (Some(GameSide::White), reason),
+        (Err((_, reason)), _) => (Some(GameSide::Black), reason),
+        (Ok(_), GameState::Finished { winner_side, .. }) => (Some(*winner_side), EndReason::Five),
+        (Ok(_), _) => (None, EndReason::BoardFull),
+    };
+
+    Ok(GameOutcome {
+        game,
+        winner,
+        reason,
+        names,
+    })
+}
+
+/// Return the stones of game from the view of the side to move, in the order they were placed.
+fn board_fields(game: &Game) -> Vec<(Point, BoardField)> {
+    game.iter_steps()
+        .map(|(side, point)| {
+            let field = if *side == game.side() { BoardField::Own } else { BoardField::Opponent };
+            (*point, field)
+        })
+        .collect()
+}
+
+/// Create a game record from the outcome of a game.
+fn record_outcome(outcome: &GameOutcome, round: usize) -> Result<GameRecord, RecordError> {
+    let mut record = GameRecord::from_game(&outcome.game)?;
+
+    record.metadata.black = Some(outcome.names[0].clone());
+    record.metadata.white = Some(outcome.names[1].clone());
+    record.metadata.event = Some(format!("Engine match, game {}", round));
+    record.metadata.date = Some(today());
+    record.metadata.result = Some(match outcome.winner {
+        Some(GameSide::Black) => String::from("B+"),
+        // Matches are played by two brains, so any other winner is white
+        Some(_) => String::from("W+"),
+        None => String::from("Draw"),
+    });
+    record.comment = Some(format!("Game ended by {}", outcome.reason.describe()));
+
+    Ok(record)
+}
+
+/// Return the current date in the format `YYYY-MM-DD`.
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or_default() as i64;
+
+    // Convert days since the epoch to a civil date, from Howard Hinnant's algorithm
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let config = match MatchConfig::from_args(&args) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(2);
+        },
+    };
+
+    if let Err(err) = run_match(&config) {
+        eprintln!("Match failed: {}", err);
+        process::exit(1);
+    }
+}
+
+/// Play all the games of a match, and write the results to the output directory.
+fn run_match(config: &MatchConfig) -> io::Result<()> {
+    fs::create_dir_all(&config.output)?;
+    let mut results = fs::File::create(config.output.join("results.txt"))?;
+
+    // Wins, draws and losses of the first brain
+    let mut score = [0; 3];
+    let mut names = [String::new(), String::new()];
+
+    for round in 1..=config.games {
+        // Alternate colors, so the first brain plays black in odd games
+        let first_is_black = round % 2 == 1;
+        let paths = if first_is_black {
+            [config.brains[0].as_path(), config.brains[1].as_path()]
+        } else {
+            [config.brains[1].as_path(), config.brains[0].as_path()]
+        };
+
+        // Play each opening twice in a row, so both brains get each side of it
+        let opening = match config.openings.len() {
+            0 => &[][..],
+            len => &config.openings[(round - 1) / 2 % len][..],
+        };
+
+        let outcome = play_game(config, paths, opening)?;
+        let record = record_outcome(&outcome, round)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let sgf = record.to_sgf().map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        fs::write(config.output.join(format!("game-{:03}.sgf", round)), sgf)?;
+
+        let first_side = if first_is_black { GameSide::Black } else { GameSide::White };
+        match outcome.winner {
+            Some(side) if side == first_side => score[0] += 1,
+            Some(_) => score[2] += 1,
+            None => score[1] += 1,
+        }
+        names = if first_is_black {
+            outcome.names.clone()
+        } else {
+            [outcome.names[1].clone(), outcome.names[0].clone()]
+        };
+
+        let mut line = format!(
+            "game {}: black {}, white {}, result {}, by {}, {} steps",
+            round,
+            outcome.names[0],
+            outcome.names[1],
+            record.metadata.result.as_deref().unwrap_or("?"),
+            outcome.reason.describe(),
+            outcome.game.step_count(),
+        );
+        if !opening.is_empty() {
+            line.push_str(&format!(", opening {}", format_sgf_points(opening)));
+        }
+        println!("{}", line);
+        writeln!(results, "{}", line)?;
+    }
+
+    let summary = format!(
+        "{} vs {}: {} wins, {} draws, {} losses",
+        names[0], names[1], score[0], score[1], score[2],
+    );
+    println!("{}", summary);
+    writeln!(results, "{}", summary)?;
+
+    Ok(())
+}
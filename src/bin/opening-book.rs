@@ -0,0 +1,111 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use connect_5_rs::{Game, GameRecord, OpeningBook, parse_sgf_points};
+
+static USAGE: &str = "\
+Usage:
+  opening-book build <book> <sgf files...> [--steps <n>]
+  opening-book merge <book> <input books...>
+  opening-book query <book> [position] [--size <n>]
+
+Positions are written as SGF coordinates of the steps in order, e.g. hhig.
+Building reads the main line of every game in the SGF files, up to 12 steps by default.";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        Some("build") => build(&args[1..]),
+        Some("merge") => merge(&args[1..]),
+        Some("query") => query(&args[1..]),
+        _ => Err(String::from("expected a command")),
+    };
+
+    if let Err(message) = result {
+        eprintln!("{}\n\n{}", message, USAGE);
+        process::exit(2);
+    }
+}
+
+/// Split arguments into positional arguments and the value of an option, if given.
+fn split_option(args: &[String], name: &str) -> Result<(Vec<String>, Option<usize>), String> {
+    let mut positional = vec![];
+    let mut value = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if arg == name {
+            let text = args.next().ok_or_else(|| format!("missing value for {}", name))?;
+            value = Some(text.parse().map_err(|_| format!("invalid number {}", text))?);
+        } else {
+            positional.push(arg.clone());
+        }
+    }
+
+    Ok((positional, value))
+}
+
+fn build(args: &[String]) -> Result<(), String> {
+    let (args, steps) = split_option(args, "--steps")?;
+    let (output, inputs) = args.split_first().ok_or("missing book path")?;
+
+    let mut book = OpeningBook::new();
+    let mut count = 0;
+    for input in inputs {
+        let text = fs::read_to_string(input).map_err(|err| format!("{}: {}", input, err))?;
+        let records = GameRecord::from_sgf_collection(&text).map_err(|err| format!("{}: {}", input, err))?;
+
+        for record in &records {
+            book.add_record(record, steps.unwrap_or(12));
+        }
+        count += records.len();
+    }
+
+    book.save(output).map_err(|err| format!("{}: {}", output, err))?;
+    println!("Built book with {} positions from {} games.", book.len(), count);
+    Ok(())
+}
+
+fn merge(args: &[String]) -> Result<(), String> {
+    let (output, inputs) = args.split_first().ok_or("missing book path")?;
+
+    let mut book = OpeningBook::new();
+    for input in inputs {
+        let other = OpeningBook::load(input).map_err(|err| format!("{}: {}", input, err))?;
+        book.merge(&other);
+    }
+
+    book.save(output).map_err(|err| format!("{}: {}", output, err))?;
+    println!("Merged book with {} positions.", book.len());
+    Ok(())
+}
+
+fn query(args: &[String]) -> Result<(), String> {
+    let (args, size) = split_option(args, "--size")?;
+    let path = args.first().ok_or("missing book path")?;
+    let book = OpeningBook::load(path).map_err(|err| format!("{}: {}", path, err))?;
+
+    let position = args.get(1).map(String::as_str).unwrap_or("");
+    let steps = parse_sgf_points(position).ok_or("invalid position")?;
+    let game = Game::from_steps(size.unwrap_or(15), &steps).map_err(|err| err.to_string())?;
+
+    let moves = book.lookup(&game);
+    if moves.is_empty() {
+        println!("Position is not in the book.");
+    }
+    for m in moves {
+        println!(
+            "{:2} {:2}  weight {:5}  +{} ={} -{}  score {}",
+            m.point.x,
+            m.point.y,
+            m.weight,
+            m.wins,
+            m.draws,
+            m.losses,
+            m.score().map_or(String::from("-"), |score| format!("{:.2}", score)),
+        );
+    }
+    Ok(())
+}
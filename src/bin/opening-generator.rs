@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::process;
+
+use connect_5_rs::{GameRecord, MAX_SGF_SIZE, OpeningGenerator, Rng, format_sgf_points};
+use connect_5_rs::engine::Weights;
+
+static USAGE: &str = "\
+Usage: opening-generator [options]
+
+Generate random openings near the center of the board where neither side is winning,
+as scored by the engine, for playing matches from many starting positions.
+
+Options:
+  --count <n>         number of openings to generate (default 100)
+  --stones <n>        number of stones in each opening (default 4)
+  --size <n>          board size (default 15)
+  --radius <n>        largest distance of the stones from the center (default 3)
+  --balance <n>       largest score of a balanced opening (default 300)
+  --depth <n>         depth searched to score each opening (default 2)
+  --seed <n>          seed of the random openings (default 1)
+  --format <format>   positions or sgf (default positions)
+  --weights <file>    pattern weights of the engine (default built-in weights)
+  --output <file>     file to write the openings to (default standard output)
+
+Positions are written one on each line as SGF coordinates of the steps in order, e.g. hhig.
+Both formats can be read by connect-5-rs --opening and gomocup-match --openings.";
+
+/// Settings of the generated openings.
+struct GeneratorConfig {
+    count: usize,
+    stones: usize,
+    size: usize,
+    radius: usize,
+    balance: i32,
+    depth: usize,
+    seed: u64,
+    sgf: bool,
+    weights: Weights,
+    output: Option<String>,
+}
+
+impl GeneratorConfig {
+    /// Read the settings from command line arguments.
+    fn from_args(args: &[String]) -> Result<GeneratorConfig, String> {
+        let mut config = GeneratorConfig {
+            count: 100,
+            stones: 4,
+            size: 15,
+            radius: 3,
+            balance: 300,
+            depth: 2,
+            seed: 1,
+            sgf: false,
+            weights: Weights::default(),
+            output: None,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next().ok_or_else(|| format!("missing value for {}", name))
+            };
+            let number = |text: &String| {
+                text.parse::<u64>().map_err(|_| format!("invalid number {}", text))
+            };
+
+            match &arg[..] {
+                "--count" => config.count = number(value(arg)?)? as usize,
+                "--stones" => config.stones = number(value(arg)?)? as usize,
+                "--size" => config.size = number(value(arg)?)? as usize,
+                "--radius" => config.radius = number(value(arg)?)? as usize,
+                "--balance" => config.balance = number(value(arg)?)?.min(i32::MAX as u64) as i32,
+                "--depth" => config.depth = number(value(arg)?)? as usize,
+                "--seed" => config.seed = number(value(arg)?)?,
+                "--format" => config.sgf = match &value(arg)?[..] {
+                    "positions" => false,
+                    "sgf" => true,
+                    other => return Err(format!("unknown format {}", other)),
+                },
+                "--weights" => {
+                    let path = value(arg)?;
+                    config.weights = Weights::load(path).map_err(|err| format!("{}: {}", path, err))?;
+                },
+                "--output" => config.output = Some(value(arg)?.clone()),
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+
+        if !(5..=MAX_SGF_SIZE).contains(&config.size) {
+            return Err(format!("board size must be between 5 and {}", MAX_SGF_SIZE));
+        }
+
+        Ok(config)
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let config = match GeneratorConfig::from_args(&args) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(2);
+        },
+    };
+
+    let mut generator = OpeningGenerator::new(config.size, config.stones);
+    generator.set_radius(config.radius);
+    generator.set_balance(config.balance);
+    generator.set_depth(config.depth);
+    generator.set_weights(config.weights.clone());
+
+    let mut rng = Rng::new(config.seed);
+    let mut seen = HashSet::new();
+    let mut text = String::new();
+    let mut count = 0;
+    let mut duplicates = 0;
+
+    while count < config.count {
+        let game = match generator.generate(&mut rng) {
+            Some(game) => game,
+            None => {
+                eprintln!("Could not find a balanced opening, generated {} of {}.", count, config.count);
+                break;
+            },
+        };
+
+        // The same position may be reached by placing the stones in another order
+        if !seen.insert(game.position_hash()) {
+            duplicates += 1;
+            if duplicates > config.count * 10 {
+                eprintln!("Too few different openings, generated {} of {}.", count, config.count);
+                break;
+            }
+            continue;
+        }
+        count += 1;
+
+        if config.sgf {
+            let sgf = GameRecord::from_game(&game).and_then(|mut record| {
+                record.metadata.event = Some(format!("Balanced opening {}", count));
+                record.to_sgf()
+            });
+            match sgf {
+                Ok(sgf) => text.push_str(&sgf),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    process::exit(1);
+                },
+            }
+            text.push('\n');
+        } else {
+            let steps: Vec<_> = game.iter_steps().map(|(_, point)| *point).collect();
+            text.push_str(&format_sgf_points(&steps));
+            text.push('\n');
+        }
+    }
+
+    match &config.output {
+        Some(path) => if let Err(err) = fs::write(path, text) {
+            eprintln!("{}: {}", path, err);
+            process::exit(1);
+        },
+        None => print!("{}", text),
+    }
+}
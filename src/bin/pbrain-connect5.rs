@@ -0,0 +1,225 @@
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+use connect_5_rs::{Game, GameRules, GameState, Point, RuleSet};
+use connect_5_rs::engine::{AlphaBeta, Engine, SearchLimits};
+use connect_5_rs::protocol::{self, BoardField, Command};
+
+/// The time for each turn, used until the manager sends `timeout_turn`.
+static DEFAULT_TURN_TIME: Duration = Duration::from_secs(5);
+
+/// The number of turns the remaining match time is expected to be spread over.
+static EXPECTED_TURNS: u32 = 20;
+
+/// The bit of the `rule` setting that selects exactly five in a row to win for both sides.
+static EXACT_FIVE_RULE_BIT: u64 = 1;
+
+/// The bit of the `rule` setting that selects renju.
+static RENJU_RULE_BIT: u64 = 4;
+
+/// A Gomocup brain backed by the built-in alpha-beta engine.
+///
+/// Piskvork and other managers talk to the brain over standard input and output,
+/// one command per line.
+fn main() {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut brain = Brain::new();
+
+    while let Some(Ok(line)) = lines.next() {
+        let mut command = Command::parse(&line);
+
+        // The stones of a board follow on separate lines, until DONE
+        if let Command::Board(stones) = &mut command {
+            while let Some(Ok(line)) = lines.next() {
+                match protocol::parse_board_line(&line) {
+                    Some(stone) => stones.push(stone),
+                    None => break,
+                }
+            }
+        }
+
+        if let Command::End = command {
+            break;
+        }
+
+        if let Some(reply) = brain.handle(command) {
+            println!("{}", reply);
+            io::stdout().flush().expect("Failed to write reply");
+        }
+    }
+}
+
+/// The state of the brain between commands.
+struct Brain {
+    /// The current game, if one was started.
+    game: Option<Game>,
+    /// The engine used to choose steps.
+    engine: AlphaBeta,
+    /// The time limit for each turn.
+    turn_time: Duration,
+    /// The time left for the whole match, if limited.
+    time_left: Option<Duration>,
+    /// The rules for new games.
+    rules: GameRules,
+}
+
+impl Brain {
+    fn new() -> Brain {
+        Brain {
+            game: None,
+            engine: AlphaBeta::new(),
+            turn_time: DEFAULT_TURN_TIME,
+            time_left: None,
+            rules: GameRules::default(),
+        }
+    }
+
+    /// Handle a command, and return the reply to send to the manager, if any.
+    fn handle(&mut self, command: Command) -> Option<String> {
+        match command {
+            Command::Start(size) => {
+                self.game = Some(Game::with_rules(size, self.rules));
+                Some(String::from("OK"))
+            },
+            Command::RectStart(_, _) => Some(String::from("ERROR only square boards are supported")),
+            Command::Restart => {
+                let size = match &self.game {
+                    Some(game) => game.size(),
+                    None => return Some(String::from("ERROR game not started")),
+                };
+                self.game = Some(Game::with_rules(size, self.rules));
+                Some(String::from("OK"))
+            },
+            Command::Begin => Some(self.play()),
+            Command::Turn(point) => {
+                let game = match &mut self.game {
+                    Some(game) => game,
+                    None => return Some(String::from("ERROR game not started")),
+                };
+                match game.add_step(point) {
+                    Ok(_) => Some(self.play()),
+                    Err(err) => Some(format!("ERROR {}", err)),
+                }
+            },
+            Command::Board(stones) => {
+                let size = match &self.game {
+                    Some(game) => game.size(),
+                    None => return Some(String::from("ERROR game not started")),
+                };
+                match game_from_board(size, self.rules, &stones) {
+                    Ok(game) => {
+                        self.game = Some(game);
+                        Some(self.play())
+                    },
+                    Err(message) => Some(format!("ERROR {}", message)),
+                }
+            },
+            Command::Info(key, value) => self.set_info(&key, &value),
+            Command::Takeback(point) => {
+                let game = match &mut self.game {
+                    Some(game) => game,
+                    None => return Some(String::from("ERROR game not started")),
+                };
+                if game.last_step() == Some(point) {
+                    game.undo_step();
+                    Some(String::from("OK"))
+                } else {
+                    Some(String::from("ERROR can only take back the last step"))
+                }
+            },
+            Command::About => Some(format!(
+                "name=\"connect-5-rs\", version=\"{}\", author=\"{}\"",
+                env!("CARGO_PKG_VERSION"),
+                env!("CARGO_PKG_AUTHORS"),
+            )),
+            Command::End => None,
+            Command::Unknown(line) => Some(format!("UNKNOWN {}", line)),
+        }
+    }
+
+    /// Apply a setting from an `INFO` command, and return the reply if the setting
+    /// is not supported. Unknown settings are ignored.
+    fn set_info(&mut self, key: &str, value: &str) -> Option<String> {
+        let number: u64 = value.parse().ok()?;
+
+        match key {
+            // A turn time of 0 means play as fast as possible
+            "timeout_turn" => self.turn_time = Duration::from_millis(number.max(1)),
+            "time_left" => self.time_left = Some(Duration::from_millis(number)),
+            // Takes effect from the next game. Renju already needs exactly five for black
+            "rule" if number & RENJU_RULE_BIT != 0 => self.rules.rule_set = RuleSet::Renju,
+            "rule" => {
+                self.rules.rule_set = RuleSet::Freestyle;
+                if number & EXACT_FIVE_RULE_BIT != 0 {
+                    return Some(String::from("ERROR exactly five in a row is not supported, playing freestyle"));
+                }
+            },
+            _ => (),
+        }
+
+        None
+    }
+
+    /// Choose a step in the current game, play it, and return the reply for the manager.
+    fn play(&mut self) -> String {
+        let mut time = self.turn_time;
+        if let Some(time_left) = self.time_left {
+            time = time.min(time_left / EXPECTED_TURNS);
+        }
+        // Leave a margin for the time taken to read and write commands
+        let limits = SearchLimits::time(time.mul_f64(0.9));
+
+        let game = match &mut self.game {
+            Some(game) => game,
+            None => return String::from("ERROR game not started"),
+        };
+        if !matches!(game.state(), GameState::Normal) {
+            return String::from("ERROR game is over");
+        }
+
+        match self.engine.search(game, &limits) {
+            Some(result) => {
+                // Steps chosen by the engine are always valid
+                game.add_step(result.best_step).expect("engine steps should be valid");
+                protocol::format_point(&result.best_step)
+            },
+            None => String::from("ERROR no step available"),
+        }
+    }
+}
+
+/// Create a game from the stones of a `BOARD` command, where the brain is to move next.
+fn game_from_board(size: usize, rules: GameRules, stones: &[(Point, BoardField)]) -> Result<Game, &'static str> {
+    let own: Vec<Point> = stones.iter()
+        .filter(|(_, field)| *field == BoardField::Own)
+        .map(|(p, _)| *p)
+        .collect();
+    let opponent: Vec<Point> = stones.iter()
+        .filter(|(_, field)| *field == BoardField::Opponent)
+        .map(|(p, _)| *p)
+        .collect();
+
+    if own.len() + opponent.len() != stones.len() {
+        return Err("continuous game stones are not supported");
+    }
+
+    // The side with more stones moved first, or the brain if both have the same number
+    let (first, second) = if own.len() == opponent.len() {
+        (&own, &opponent)
+    } else if opponent.len() == own.len() + 1 {
+        (&opponent, &own)
+    } else {
+        return Err("invalid number of stones for each side");
+    };
+
+    let mut steps = Vec::with_capacity(stones.len());
+    for (i, point) in first.iter().enumerate() {
+        steps.push(*point);
+        if let Some(point) = second.get(i) {
+            steps.push(*point);
+        }
+    }
+
+    Game::from_steps_with_rules(size, rules, &steps).map_err(|_| "invalid stones on the board")
+}
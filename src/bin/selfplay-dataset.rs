@@ -0,0 +1,286 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process;
+use std::thread;
+
+use connect_5_rs::{Game, GameSide, GameState, MAX_SGF_SIZE, OpeningGenerator, Point, Rng, format_sgf_points};
+use connect_5_rs::engine::{AlphaBeta, Engine, SearchLimits, Weights};
+
+static USAGE: &str = "\
+Usage: selfplay-dataset <output> [options]
+
+Play engine self-play games from random balanced openings, and write every searched position
+with the side to move, the search score, the chosen step and the final result to a dataset.
+The same seed and settings always produce the same dataset, whatever the number of threads.
+
+Options:
+  --games <n>         number of games to play (default 100)
+  --depth <n>         depth searched by the engine (default 2)
+  --size <n>          board size (default 15)
+  --opening <n>       random steps near the center at the start of each game (default 4)
+  --balance <n>       largest score of an opening for it to be used (default 300)
+  --threads <n>       number of games played at the same time (default all cores)
+  --seed <n>          seed of the random openings (default 1)
+  --format <format>   jsonl or binary (default jsonl)
+  --weights <file>    pattern weights of the engine (default built-in weights)
+
+The jsonl format has one position on each line, such as
+  {\"game\":0,\"size\":15,\"position\":\"hhig\",\"side\":\"black\",\"score\":120,\"step\":\"ij\",\"result\":1}
+where the position and step are SGF coordinates, and the result is 1, 0 or -1 for a win, draw
+or loss of the side to move.
+
+The binary format starts with the bytes C5DS and a version byte of 1, followed by each game:
+  u8 size, u8 opening steps, u16 steps, i8 result (1 black won, -1 white won, 0 draw),
+  then x and y as u8 for every step, then the i32 score of every step after the opening.
+Numbers larger than a byte are little endian.";
+
+/// The number of random openings tried before using one that is not balanced.
+static MAX_OPENING_TRIES: usize = 100;
+
+/// The first bytes of a binary dataset.
+static BINARY_MAGIC: &[u8] = b"C5DS";
+
+/// The version of the binary dataset format.
+static BINARY_VERSION: u8 = 1;
+
+/// The format of a dataset file.
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum Format {
+    Jsonl,
+    Binary,
+}
+
+/// Settings of the dataset.
+struct DatasetConfig {
+    output: String,
+    games: usize,
+    depth: usize,
+    size: usize,
+    opening: usize,
+    balance: i32,
+    threads: usize,
+    seed: u64,
+    format: Format,
+    weights: Weights,
+}
+
+impl DatasetConfig {
+    /// Read the settings from command line arguments.
+    fn from_args(args: &[String]) -> Result<DatasetConfig, String> {
+        let mut output = None;
+        let mut config = DatasetConfig {
+            output: String::new(),
+            games: 100,
+            depth: 2,
+            size: 15,
+            opening: 4,
+            balance: 300,
+            threads: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            seed: 1,
+            format: Format::Jsonl,
+            weights: Weights::default(),
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next().ok_or_else(|| format!("missing value for {}", name))
+            };
+            let number = |text: &String| {
+                text.parse::<u64>().map_err(|_| format!("invalid number {}", text))
+            };
+
+            match &arg[..] {
+                "--games" => config.games = number(value(arg)?)? as usize,
+                "--depth" => config.depth = number(value(arg)?)? as usize,
+                "--size" => config.size = number(value(arg)?)? as usize,
+                "--opening" => config.opening = number(value(arg)?)? as usize,
+                "--balance" => config.balance = number(value(arg)?)?.min(i32::MAX as u64) as i32,
+                "--threads" => config.threads = (number(value(arg)?)? as usize).max(1),
+                "--seed" => config.seed = number(value(arg)?)?,
+                "--format" => config.format = match &value(arg)?[..] {
+                    "jsonl" => Format::Jsonl,
+                    "binary" => Format::Binary,
+                    other => return Err(format!("unknown format {}", other)),
+                },
+                "--weights" => {
+                    let path = value(arg)?;
+                    config.weights = Weights::load(path).map_err(|err| format!("{}: {}", path, err))?;
+                },
+                _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+                _ if output.is_none() => output = Some(arg.clone()),
+                _ => return Err(format!("unexpected argument {}", arg)),
+            }
+        }
+
+        config.output = output.ok_or("missing output path")?;
+        // Positions are written as SGF coordinates, which only reach that far
+        if !(5..=MAX_SGF_SIZE).contains(&config.size) {
+            return Err(format!("board size must be between 5 and {}", MAX_SGF_SIZE));
+        }
+        if config.opening > 255 || config.opening >= config.size * config.size / 2 {
+            return Err(String::from("too many opening steps for the board"));
+        }
+
+        Ok(config)
+    }
+}
+
+/// A finished self-play game.
+struct GameData {
+    size: usize,
+    /// The number of random steps at the start of the game, which have no scores.
+    opening: usize,
+    steps: Vec<Point>,
+    /// The search score of every step after the opening, from the view of the side to move.
+    scores: Vec<i32>,
+    winner: Option<GameSide>,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let config = match DatasetConfig::from_args(&args) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(2);
+        },
+    };
+
+    let games = play_games(&config);
+    let positions: usize = games.iter().map(|game| game.scores.len()).sum();
+
+    let result = File::create(&config.output).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        match config.format {
+            Format::Jsonl => write_jsonl(&mut writer, &games)?,
+            Format::Binary => write_binary(&mut writer, &games)?,
+        }
+        writer.flush()
+    });
+
+    if let Err(err) = result {
+        eprintln!("{}: {}", config.output, err);
+        process::exit(1);
+    }
+
+    println!("Wrote {} games with {} positions to {}", games.len(), positions, config.output);
+}
+
+/// Play all games, spread over the threads, and return them in order.
+///
+/// Every game has its own seed derived from its number, so the games do not depend on
+/// which thread plays them.
+fn play_games(config: &DatasetConfig) -> Vec<GameData> {
+    let threads = config.threads.min(config.games).max(1);
+
+    let mut games: Vec<(usize, GameData)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|start| scope.spawn(move || {
+                (start..config.games)
+                    .step_by(threads)
+                    .map(|index| (index, play_game(config, index)))
+                    .collect::<Vec<_>>()
+            }))
+            .collect();
+
+        handles.into_iter()
+            .flat_map(|handle| handle.join().expect("self-play threads should not panic"))
+            .collect()
+    });
+
+    games.sort_by_key(|(index, _)| *index);
+    games.into_iter().map(|(_, game)| game).collect()
+}
+
+/// Play the game with index, from a balanced random opening.
+fn play_game(config: &DatasetConfig, index: usize) -> GameData {
+    let mut rng = Rng::new(config.seed ^ (index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    let mut engine = AlphaBeta::with_weights(config.weights.clone());
+    let limits = SearchLimits::depth(config.depth);
+
+    let mut generator = OpeningGenerator::new(config.size, config.opening);
+    generator.set_balance(config.balance);
+    generator.set_depth(config.depth);
+    generator.set_max_tries(MAX_OPENING_TRIES);
+    generator.set_weights(config.weights.clone());
+
+    let mut game = generator.generate(&mut rng)
+        .or_else(|| generator.random_position(&mut rng))
+        .unwrap_or_else(|| Game::new(config.size));
+
+    let opening = game.step_count();
+    let mut scores = vec![];
+    while let Some(result) = engine.search(&game, &limits) {
+        scores.push(result.score);
+        game.add_step(result.best_step).expect("engine steps should be valid");
+    }
+
+    GameData {
+        size: config.size,
+        opening,
+        steps: game.iter_steps().map(|(_, point)| *point).collect(),
+        scores,
+        winner: match game.state() {
+            GameState::Finished { winner_side, .. } => Some(*winner_side),
+            _ => None,
+        },
+    }
+}
+
+/// Write every searched position of games, one JSON object on each line.
+fn write_jsonl<W: Write>(writer: &mut W, games: &[GameData]) -> io::Result<()> {
+    for (index, game) in games.iter().enumerate() {
+        for (i, score) in game.scores.iter().enumerate() {
+            let count = game.opening + i;
+            // Black always takes the first step
+            let side = if count % 2 == 0 { GameSide::Black } else { GameSide::White };
+            let result = match game.winner {
+                Some(winner) if winner == side => 1,
+                Some(_) => -1,
+                None => 0,
+            };
+
+            writeln!(
+                writer,
+                "{{\"game\":{},\"size\":{},\"position\":\"{}\",\"side\":\"{}\",\"score\":{},\"step\":\"{}\",\"result\":{}}}",
+                index,
+                game.size,
+                format_sgf_points(&game.steps[..count]),
+                if side == GameSide::Black { "black" } else { "white" },
+                score,
+                format_sgf_points(&game.steps[count..=count]),
+                result,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Write games in the binary format described in the usage.
+fn write_binary<W: Write>(writer: &mut W, games: &[GameData]) -> io::Result<()> {
+    writer.write_all(BINARY_MAGIC)?;
+    writer.write_all(&[BINARY_VERSION])?;
+
+    for game in games {
+        let result: i8 = match game.winner {
+            Some(GameSide::Black) => 1,
+            // Self-play games have two players, so any other winner is white
+            Some(_) => -1,
+            None => 0,
+        };
+
+        writer.write_all(&[game.size as u8, game.opening as u8])?;
+        writer.write_all(&(game.steps.len() as u16).to_le_bytes())?;
+        writer.write_all(&result.to_le_bytes())?;
+        for step in &game.steps {
+            writer.write_all(&[step.x as u8, step.y as u8])?;
+        }
+        for score in &game.scores {
+            writer.write_all(&score.to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,267 @@
+use std::env;
+use std::process;
+
+use connect_5_rs::{Game, GameSide, GameState, OpeningGenerator, Point, Rng};
+use connect_5_rs::engine::{AlphaBeta, Engine, Pattern, SearchLimits, Weights, pattern_features};
+
+static USAGE: &str = "\
+Usage: train-weights <output> [options]
+
+Tune the pattern weights of the evaluation by playing engine-vs-engine games, then fitting
+the weights to the outcomes of the games with Texel tuning. The weights are written to the
+output file after every round, and can be loaded by the engines with --weights.
+
+Options:
+  --rounds <n>        rounds of playing and tuning (default 3)
+  --games <n>         games played in each round (default 50)
+  --depth <n>         depth searched by the engines (default 2)
+  --size <n>          board size (default 15)
+  --opening <n>       random steps near the center at the start of each game (default 4)
+  --iterations <n>    maximum passes over the weights in each round of tuning (default 20)
+  --seed <n>          seed of the random openings (default 1)
+  --weights <file>    weights to start from (default built-in weights)";
+
+/// The factor each weight is multiplied or divided by when looking for a better value.
+static STEP_FACTOR: f64 = 1.1;
+
+/// Settings of the training.
+struct TrainConfig {
+    output: String,
+    rounds: usize,
+    games: usize,
+    depth: usize,
+    size: usize,
+    opening: usize,
+    iterations: usize,
+    seed: u64,
+    weights: Weights,
+}
+
+impl TrainConfig {
+    /// Read the settings from command line arguments.
+    fn from_args(args: &[String]) -> Result<TrainConfig, String> {
+        let mut output = None;
+        let mut config = TrainConfig {
+            output: String::new(),
+            rounds: 3,
+            games: 50,
+            depth: 2,
+            size: 15,
+            opening: 4,
+            iterations: 20,
+            seed: 1,
+            weights: Weights::default(),
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next().ok_or_else(|| format!("missing value for {}", name))
+            };
+            let number = |text: &String| {
+                text.parse::<u64>().map_err(|_| format!("invalid number {}", text))
+            };
+
+            match &arg[..] {
+                "--rounds" => config.rounds = number(value(arg)?)? as usize,
+                "--games" => config.games = number(value(arg)?)? as usize,
+                "--depth" => config.depth = number(value(arg)?)? as usize,
+                "--size" => config.size = number(value(arg)?)? as usize,
+                "--opening" => config.opening = number(value(arg)?)? as usize,
+                "--iterations" => config.iterations = number(value(arg)?)? as usize,
+                "--seed" => config.seed = number(value(arg)?)?,
+                "--weights" => {
+                    let path = value(arg)?;
+                    config.weights = Weights::load(path).map_err(|err| format!("{}: {}", path, err))?;
+                },
+                _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+                _ if output.is_none() => output = Some(arg.clone()),
+                _ => return Err(format!("unexpected argument {}", arg)),
+            }
+        }
+
+        config.output = output.ok_or("missing output path")?;
+        if config.size < 5 {
+            return Err(String::from("board size must be at least 5"));
+        }
+
+        Ok(config)
+    }
+}
+
+/// A position from a training game, with the pattern features from the view of the side to move
+/// and the outcome for that side: 1 for a win, 0.5 for a draw and 0 for a loss.
+struct Sample {
+    features: [i32; 7],
+    result: f64,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let config = match TrainConfig::from_args(&args) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(2);
+        },
+    };
+
+    let mut rng = Rng::new(config.seed);
+    let mut weights = config.weights.clone();
+
+    for round in 1..=config.rounds {
+        let mut samples = vec![];
+        let (mut black_wins, mut white_wins) = (0, 0);
+        for _ in 0..config.games {
+            let (game, positions) = play_game(&config, &weights, &mut rng);
+            match game.state() {
+                GameState::Finished { winner_side: GameSide::Black, .. } => black_wins += 1,
+                GameState::Finished { winner_side: GameSide::White, .. } => white_wins += 1,
+                _ => (),
+            }
+            samples.extend(label(&game, positions));
+        }
+
+        println!("Round {}: {} games, black {} - white {}, {} positions",
+                 round, config.games, black_wins, white_wins, samples.len());
+
+        if samples.is_empty() {
+            continue;
+        }
+
+        let scale = fit_scale(&samples, &weights);
+        let before = error(&samples, &weights, scale);
+        tune(&samples, &mut weights, scale, config.iterations);
+        let after = error(&samples, &weights, scale);
+
+        println!("  scale {:.0}, error {:.5} -> {:.5}", scale, before, after);
+        for pattern in &Pattern::ALL {
+            println!("  {:<10} {}", pattern.name(), weights.get(*pattern));
+        }
+
+        if let Err(err) = weights.save(&config.output) {
+            eprintln!("{}: {}", config.output, err);
+            process::exit(1);
+        }
+    }
+}
+
+/// Play a game between two engines with weights, starting with random steps near the center.
+/// Return the finished game and the positions to learn from, as the number of steps played.
+fn play_game(config: &TrainConfig, weights: &Weights, rng: &mut Rng) -> (Game, Vec<usize>) {
+    let mut positions = vec![];
+    let mut game = OpeningGenerator::new(config.size, config.opening)
+        .random_position(rng)
+        .unwrap_or_else(|| Game::new(config.size));
+
+    let mut engine = AlphaBeta::with_weights(weights.clone());
+    let limits = SearchLimits::depth(config.depth);
+
+    while let Some(result) = engine.search(&game, &limits) {
+        if is_quiet(&game) {
+            positions.push(game.step_count());
+        }
+        game.add_step(result.best_step).expect("engine steps should be valid");
+    }
+
+    (game, positions)
+}
+
+/// Return true if neither side can make five on the next step, otherwise false.
+/// Positions with such threats are decided by the search rather than the evaluation.
+fn is_quiet(game: &Game) -> bool {
+    game.iter_points()
+        .filter(|p| game.spot(p).is_empty())
+        .all(|p| !game.is_winning_step(p, GameSide::Black) && !game.is_winning_step(p, GameSide::White))
+}
+
+/// Return the samples of the positions after the given numbers of steps of the finished game.
+fn label(game: &Game, positions: Vec<usize>) -> Vec<Sample> {
+    let winner = match game.state() {
+        GameState::Finished { winner_side, .. } => Some(*winner_side),
+        _ => None,
+    };
+    let steps: Vec<Point> = game.iter_steps().map(|(_, point)| *point).collect();
+
+    positions.into_iter()
+        .filter_map(|count| {
+            let position = Game::from_steps(game.size(), &steps[..count]).ok()?;
+            let result = match winner {
+                Some(winner) if winner == position.side() => 1.0,
+                Some(_) => 0.0,
+                None => 0.5,
+            };
+
+            Some(Sample {
+                features: pattern_features(&position),
+                result,
+            })
+        })
+        .collect()
+}
+
+/// Return the evaluation of the sample with weights.
+fn evaluate(sample: &Sample, weights: &Weights) -> f64 {
+    sample.features.iter()
+        .zip(&Pattern::ALL)
+        .map(|(count, pattern)| (count * weights.get(*pattern)) as f64)
+        .sum()
+}
+
+/// Return the mean squared difference between the outcomes of the samples and the
+/// win probabilities predicted from their evaluations, where scale is the evaluation
+/// difference that makes a win e (about 2.7) times as likely.
+fn error(samples: &[Sample], weights: &Weights, scale: f64) -> f64 {
+    let total: f64 = samples.iter()
+        .map(|sample| {
+            let predicted = 1.0 / (1.0 + (-evaluate(sample, weights) / scale).exp());
+            (sample.result - predicted).powi(2)
+        })
+        .sum();
+
+    total / samples.len() as f64
+}
+
+/// Return the scale that best predicts the outcomes of the samples with the current weights,
+/// so that tuning changes the weights rather than only how they are scaled.
+fn fit_scale(samples: &[Sample], weights: &Weights) -> f64 {
+    (0..16)
+        .map(|i| 10.0 * 2f64.powi(i))
+        .map(|scale| (scale, error(samples, weights, scale)))
+        .fold((1.0, f64::INFINITY), |best, next| if next.1 < best.1 { next } else { best })
+        .0
+}
+
+/// Tune weights to reduce the error on the samples, by repeatedly trying a larger and a smaller
+/// value for each weight and keeping any that improves. The weight of a five is kept as it is,
+/// since it only decides how the other weights are scaled.
+fn tune(samples: &[Sample], weights: &mut Weights, scale: f64, iterations: usize) {
+    let mut best = error(samples, weights, scale);
+
+    for _ in 0..iterations {
+        let mut improved = false;
+
+        for pattern in Pattern::ALL.iter().skip(1) {
+            let value = weights.get(*pattern);
+            let larger = ((value as f64 * STEP_FACTOR).round() as i32).max(value + 1);
+            let smaller = ((value as f64 / STEP_FACTOR).round() as i32).min(value - 1).max(0);
+
+            for candidate in [larger, smaller].iter() {
+                weights.set(*pattern, *candidate);
+                let candidate_error = error(samples, weights, scale);
+                if candidate_error < best {
+                    best = candidate_error;
+                    improved = true;
+                    break;
+                }
+                weights.set(*pattern, value);
+            }
+        }
+
+        if !improved {
+            break;
+        }
+    }
+}
+
@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crate::{Game, GameRecord, GameSide, GameState, Point, Rng, Symmetry};
+
+/// The bytes at the start of every book file.
+static BOOK_MAGIC: &[u8; 4] = b"C5BK";
+
+/// The version of the book file format. Books of version 1 have no layers, and are still read.
+static BOOK_VERSION: u8 = 2;
+
+/// A candidate step in an opening book, with statistics from the games it was played in.
+/// Wins, draws and losses are counted from the view of the side that plays the step.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct BookMove {
+    /// The point of the step.
+    pub point: Point,
+    /// The weight of the step, used when choosing between steps.
+    pub weight: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl BookMove {
+    fn new(point: Point) -> BookMove {
+        BookMove {
+            point,
+            weight: 0,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+        }
+    }
+
+    /// Return the average score of the step, from 0 for always losing to 1 for always winning,
+    /// or `None` if there are no finished games with the step.
+    pub fn score(&self) -> Option<f64> {
+        let games = self.wins + self.draws + self.losses;
+        if games == 0 {
+            None
+        } else {
+            Some((self.wins as f64 + self.draws as f64 / 2.0) / games as f64)
+        }
+    }
+
+    /// Add the statistics of another step at the same point.
+    fn merge(&mut self, other: &BookMove) {
+        self.weight = self.weight.saturating_add(other.weight);
+        self.wins = self.wins.saturating_add(other.wins);
+        self.draws = self.draws.saturating_add(other.draws);
+        self.losses = self.losses.saturating_add(other.losses);
+    }
+}
+
+/// The result of a game from the view of the side that played a step.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum BookResult {
+    Win,
+    Draw,
+    Loss,
+    /// The game did not finish.
+    Unknown,
+}
+
+/// An error that is caused by reading an invalid book file.
+#[derive(Debug)]
+pub enum BookError {
+    /// The file could not be read.
+    Io(io::Error),
+
+    /// The file is not a book, or is from an unsupported version.
+    InvalidFormat,
+}
+
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BookError::Io(err) => write!(f, "cannot read book: {}", err),
+            BookError::InvalidFormat => write!(f, "invalid book format"),
+        }
+    }
+}
+
+impl error::Error for BookError {}
+
+impl From<io::Error> for BookError {
+    fn from(err: io::Error) -> BookError {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            BookError::InvalidFormat
+        } else {
+            BookError::Io(err)
+        }
+    }
+}
+
+/// An opening book, mapping positions to candidate steps.
+///
+/// Positions are keyed by their canonical hash, so a step learned in one position is also
+/// found in every position that is a rotation or reflection of it.
+#[derive(PartialEq, Clone, Default, Debug)]
+pub struct OpeningBook {
+    /// The candidate steps of each position, with points in the canonical form of the position.
+    entries: HashMap<u64, Vec<BookMove>>,
+}
+
+// Initializers
+impl OpeningBook {
+    /// Create an empty book.
+    pub fn new() -> OpeningBook {
+        OpeningBook::default()
+    }
+
+    /// Create a book from the first max_steps steps of every game.
+    pub fn from_games<'a, I>(games: I, max_steps: usize) -> OpeningBook
+    where
+        I: IntoIterator<Item=&'a Game>
+    {
+        let mut book = OpeningBook::new();
+        for game in games {
+            book.add_game(game, max_steps);
+        }
+        book
+    }
+
+    /// Read a book from a reader, in the format written by `write_to`.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<OpeningBook, BookError> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        let version = read_u8(reader)?;
+        if &magic != BOOK_MAGIC || !(1..=BOOK_VERSION).contains(&version) {
+            return Err(BookError::InvalidFormat);
+        }
+
+        let mut book = OpeningBook::new();
+        let entry_count = read_u32(reader)?;
+
+        for _ in 0..entry_count {
+            let hash = read_u64(reader)?;
+            let move_count = read_u16(reader)?;
+            let mut moves = Vec::with_capacity(move_count as usize);
+
+            for _ in 0..move_count {
+                let x = read_u8(reader)? as usize;
+                let y = read_u8(reader)? as usize;
+                let z = if version >= 2 { read_u8(reader)? as usize } else { 0 };
+                moves.push(BookMove {
+                    point: Point::with_z(x, y, z),
+                    weight: read_u32(reader)?,
+                    wins: read_u32(reader)?,
+                    draws: read_u32(reader)?,
+                    losses: read_u32(reader)?,
+                });
+            }
+
+            book.entries.insert(hash, moves);
+        }
+
+        Ok(book)
+    }
+
+    /// Read a book from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<OpeningBook, BookError> {
+        let mut file = io::BufReader::new(fs::File::open(path)?);
+        OpeningBook::read_from(&mut file)
+    }
+}
+
+// Public methods
+impl OpeningBook {
+    /// Get the number of positions in the book.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Return true if there are no positions in the book, otherwise false.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Add the first max_steps steps of a game to the book,
+    /// with the result of the game from the view of the side that played each step.
+    pub fn add_game(&mut self, game: &Game, max_steps: usize) {
+        let winner = match game.state() {
+            GameState::Finished { winner_side, .. } => Some(Some(*winner_side)),
+            GameState::BoardFull | GameState::NoWinPossible => Some(None),
+            GameState::Normal => None,
+        };
+
+        let steps: Vec<Point> = game.iter_steps().map(|(_, p)| *p).collect();
+        self.add_steps(game.starting_position(), &steps, winner, max_steps);
+    }
+
+    /// Add the first max_steps steps of the main line of a record to the book.
+    ///
+    /// The result is read from the metadata of the record if possible, since games may end
+    /// for other reasons such as a timeout, otherwise it is derived from the steps.
+    pub fn add_record(&mut self, record: &GameRecord, max_steps: usize) {
+        let winner = match record.metadata.result.as_deref().map(|r| r.to_ascii_uppercase()) {
+            Some(ref r) if r.starts_with("B+") => Some(Some(GameSide::Black)),
+            Some(ref r) if r.starts_with("W+") => Some(Some(GameSide::White)),
+            Some(ref r) if r == "0" || r == "DRAW" => Some(None),
+            _ => match record.game_at(&vec![0; record.main_line().len()]).as_ref().map(Game::state) {
+                Some(GameState::Finished { winner_side, .. }) => Some(Some(*winner_side)),
+                Some(GameState::BoardFull) | Some(GameState::NoWinPossible) => Some(None),
+                Some(GameState::Normal) | None => None,
+            },
+        };
+
+        let start = Game::with_rules(record.size(), *record.rules());
+        self.add_steps(start, &record.main_line(), winner, max_steps);
+    }
+
+    /// Add a step played in a position to the book, with the result of the game.
+    pub fn add_move(&mut self, game: &Game, point: Point, result: BookResult) {
+        let (hash, symmetries) = game.canonical_hash();
+        let point = canonical_point(&point, &symmetries, game.size());
+
+        let moves = self.entries.entry(hash).or_default();
+        let index = match moves.iter().position(|m| m.point == point) {
+            Some(index) => index,
+            None => {
+                moves.push(BookMove::new(point));
+                moves.len() - 1
+            },
+        };
+
+        let book_move = &mut moves[index];
+        book_move.weight = book_move.weight.saturating_add(1);
+        match result {
+            BookResult::Win => book_move.wins = book_move.wins.saturating_add(1),
+            BookResult::Draw => book_move.draws = book_move.draws.saturating_add(1),
+            BookResult::Loss => book_move.losses = book_move.losses.saturating_add(1),
+            BookResult::Unknown => (),
+        }
+    }
+
+    /// Return the candidate steps in a position, with points as they are on the board of the
+    /// game, from the highest weight to the lowest.
+    pub fn lookup(&self, game: &Game) -> Vec<BookMove> {
+        let (hash, symmetries) = game.canonical_hash();
+        let moves = match self.entries.get(&hash) {
+            Some(moves) => moves,
+            None => return vec![],
+        };
+
+        // Any of the symmetries maps the canonical form back to this position
+        let inverse = symmetries[0].inverse();
+        let mut moves: Vec<BookMove> = moves.iter()
+            .map(|m| BookMove {
+                point: inverse.apply(&m.point, game.size()),
+                ..*m
+            })
+            .filter(|m| game.validate_step(&m.point).is_ok())
+            .collect();
+
+        moves.sort_by_key(|m| std::cmp::Reverse(m.weight));
+        moves
+    }
+
+    /// Choose a candidate step in a position at random, with probability in proportion to
+    /// its weight. Return `None` if the position is not in the book.
+    pub fn choose(&self, game: &Game, rng: &mut Rng) -> Option<Point> {
+        let moves = self.lookup(game);
+        let total: u64 = moves.iter().map(|m| m.weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut target = rng.next_u64() % total;
+        for m in &moves {
+            if target < m.weight as u64 {
+                return Some(m.point);
+            }
+            target -= m.weight as u64;
+        }
+
+        None
+    }
+
+    /// Add all the positions and steps of another book to this book,
+    /// adding up the statistics of steps in both books.
+    pub fn merge(&mut self, other: &OpeningBook) {
+        for (hash, other_moves) in &other.entries {
+            let moves = self.entries.entry(*hash).or_default();
+
+            for other_move in other_moves {
+                match moves.iter_mut().find(|m| m.point == other_move.point) {
+                    Some(m) => m.merge(other_move),
+                    None => moves.push(*other_move),
+                }
+            }
+        }
+    }
+
+    /// Write the book to a writer in a compact binary format.
+    ///
+    /// The format starts with the bytes `C5BK`, a version byte and the number of positions.
+    /// Each position is its hash, the number of steps, and each step as the x, y and z
+    /// coordinates followed by the weight, wins, draws and losses.
+    /// Numbers are little endian, and positions are sorted by hash.
+    ///
+    /// If a coordinate is greater than 255, or there are too many positions or steps in
+    /// a position for their counts, return an error of kind `InvalidInput` without writing anything.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let fits = self.entries.len() <= u32::MAX as usize
+            && self.entries.values().all(|moves| {
+                moves.len() <= u16::MAX as usize
+                    && moves.iter().all(|m| m.point.x.max(m.point.y).max(m.point.z) <= u8::MAX as usize)
+            });
+        if !fits {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "book does not fit in the book format"));
+        }
+
+        writer.write_all(BOOK_MAGIC)?;
+        writer.write_all(&[BOOK_VERSION])?;
+        writer.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+
+        let mut hashes: Vec<&u64> = self.entries.keys().collect();
+        hashes.sort();
+
+        for hash in hashes {
+            let moves = &self.entries[hash];
+            writer.write_all(&hash.to_le_bytes())?;
+            writer.write_all(&(moves.len() as u16).to_le_bytes())?;
+
+            for m in moves {
+                writer.write_all(&[m.point.x as u8, m.point.y as u8, m.point.z as u8])?;
+                for value in &[m.weight, m.wins, m.draws, m.losses] {
+                    writer.write_all(&value.to_le_bytes())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the book to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        self.write_to(&mut file)?;
+        file.flush()
+    }
+}
+
+// Private methods
+impl OpeningBook {
+    /// Add the first max_steps steps of a game played from the position start, where winner is
+    /// `Some(None)` for a draw and `None` for an unfinished game.
+    fn add_steps(&mut self, start: Game, steps: &[Point], winner: Option<Option<GameSide>>, max_steps: usize) {
+        let mut position = start;
+
+        for point in steps.iter().take(max_steps) {
+            let result = match winner {
+                Some(Some(winner)) if winner == position.side() => BookResult::Win,
+                Some(Some(_)) => BookResult::Loss,
+                Some(None) => BookResult::Draw,
+                None => BookResult::Unknown,
+            };
+            self.add_move(&position, *point, result);
+
+            // Steps are validated by the game or record they come from, on the same starting position
+            if position.add_step(*point).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Return the canonical form of a step, given the symmetries that map the position to its
+/// canonical form. When the position is symmetric, equivalent steps map to the same point.
+fn canonical_point(point: &Point, symmetries: &[Symmetry], size: usize) -> Point {
+    symmetries.iter()
+        .map(|symmetry| symmetry.apply(point, size))
+        .min_by_key(|p| (p.y, p.x))
+        .unwrap()
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut bytes = [0; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> io::Result<u16> {
+    let mut bytes = [0; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
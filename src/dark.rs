@@ -0,0 +1,112 @@
+use crate::{Game, GameSide, GameSpot, GameState, GameStepError, Point};
+
+/// The outcome of a step tried in a dark game.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum DarkStepOutcome {
+    /// The piece was placed, as in a normal game.
+    Placed,
+
+    /// The point holds a piece of the given side, hidden from the side to move. No piece was placed,
+    /// the hidden piece is now revealed to the side to move, and it is still its turn.
+    Hidden(GameSide),
+}
+
+/// What a side sees of a dark game.
+#[derive(PartialEq, Clone, Debug)]
+pub struct DarkView {
+    /// The side the view belongs to.
+    pub side: GameSide,
+    /// The side that plays the next step.
+    pub next_side: GameSide,
+    /// The number of steps taken in the game, including those of hidden pieces.
+    pub step_count: usize,
+    /// The pieces the side can see, with their sides, in the order of the steps.
+    pub pieces: Vec<(GameSide, Point)>,
+}
+
+/// The hidden information of dark gomoku, played alongside a game.
+///
+/// Each side only sees its own pieces, and learns of a piece of another side when trying
+/// to play on its point. Blocked points are seen by every side, and the whole board is
+/// seen once the game is over. Steps must be added through `Fog::add_step` for the
+/// revealed pieces to be kept.
+#[derive(Clone, Debug, Default)]
+pub struct Fog {
+    /// The pieces revealed to each side, as the side that learned of it and the point of the piece.
+    revealed: Vec<(GameSide, Point)>,
+}
+
+// Initializers
+impl Fog {
+    /// Create a new fog, where no piece has been revealed.
+    pub fn new() -> Fog {
+        Fog {
+            revealed: vec![],
+        }
+    }
+}
+
+// Public methods
+impl Fog {
+    /// Return true if side can see the spot at point in game, otherwise false.
+    pub fn is_visible(&self, game: &Game, point: &Point, side: GameSide) -> bool {
+        match game.spot(point) {
+            GameSpot::Taken(owner) if owner != side => {
+                !matches!(game.state(), GameState::Normal) || self.revealed.contains(&(side, *point))
+            },
+            _ => true,
+        }
+    }
+
+    /// Get the spot at point in game as side sees it, which is empty if it holds a hidden piece.
+    pub fn spot(&self, game: &Game, point: &Point, side: GameSide) -> GameSpot {
+        if self.is_visible(game, point, side) {
+            game.spot(point)
+        } else {
+            GameSpot::Empty
+        }
+    }
+
+    /// Get what side sees of game.
+    pub fn view(&self, game: &Game, side: GameSide) -> DarkView {
+        DarkView {
+            side,
+            next_side: game.side(),
+            step_count: game.step_count(),
+            pieces: game.iter_steps()
+                .filter(|(_, point)| self.is_visible(game, point, side))
+                .map(|(owner, point)| (*owner, *point))
+                .collect(),
+        }
+    }
+
+    /// Try a step of the side to move in game.
+    /// If the point holds a piece hidden from the side, reveal it instead of adding the step.
+    /// If the step is otherwise invalid, do not add the step and
+    /// return a result with error of type `GameStepError`.
+    pub fn add_step(&mut self, game: &mut Game, point: Point) -> Result<DarkStepOutcome, GameStepError> {
+        let side = game.side();
+
+        if let Err(GameStepError::PointTaken) = game.validate_step(&point) {
+            if let GameSpot::Taken(owner) = self.hidden_spot(game, &point, side) {
+                self.revealed.push((side, point));
+                return Ok(DarkStepOutcome::Hidden(owner));
+            }
+        }
+
+        game.add_step(point)?;
+        Ok(DarkStepOutcome::Placed)
+    }
+}
+
+// Private methods
+impl Fog {
+    /// Get the spot at point in game if side cannot see it, otherwise an empty spot.
+    fn hidden_spot(&self, game: &Game, point: &Point, side: GameSide) -> GameSpot {
+        if self.is_visible(game, point, side) {
+            GameSpot::Empty
+        } else {
+            game.spot(point)
+        }
+    }
+}
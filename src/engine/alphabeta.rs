@@ -0,0 +1,444 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use crate::{Game, GameState, Point, Rng, RuleSet};
+use crate::engine::{
+    Difficulty,
+    Engine,
+    SearchClock,
+    SearchLimits,
+    SearchResult,
+    Weights,
+    WIN_SCORE,
+    MAX_PLY,
+    candidates,
+    evaluate,
+    is_supported,
+    pattern_at,
+    eval::{BLOCKING_STEP, forced_candidates},
+    tt::{Bound, TableEntry, TranspositionTable, DEFAULT_TABLE_SIZE},
+};
+
+/// The number of candidate steps searched at each position when no other value is given.
+static DEFAULT_MAX_CANDIDATES: usize = 12;
+
+/// The number of positions visited by a thread between checks of the limits.
+static CHECK_INTERVAL: u64 = 1024;
+
+/// An engine using iterative deepening negamax search with alpha-beta pruning,
+/// and a pattern-based evaluation of positions.
+///
+/// The search can run on several threads with Lazy SMP: every thread searches the same
+/// position, sharing what they find through a lock-free transposition table, and the result
+/// of the main thread is used. With a single thread, which is the default, the search is
+/// deterministic for limits on depth or nodes.
+///
+/// Below the `Master` difficulty, the step is chosen among the best candidates at random,
+/// using a generator seeded from the seed of the engine and the position.
+#[derive(Clone, Debug)]
+pub struct AlphaBeta {
+    /// The weights used to evaluate positions and order steps.
+    weights: Weights,
+    /// The maximum number of candidate steps searched at each position.
+    max_candidates: usize,
+    /// The number of threads to search with.
+    threads: usize,
+    /// The transposition table, shared by all threads and cleared before each search.
+    table: Arc<TranspositionTable>,
+    /// The level of playing strength.
+    difficulty: Difficulty,
+    /// The seed of the random choices made below full strength.
+    seed: u64,
+}
+
+impl AlphaBeta {
+    /// Create a new engine with default weights.
+    pub fn new() -> AlphaBeta {
+        AlphaBeta::with_weights(Weights::default())
+    }
+
+    /// Create a new engine with weights.
+    pub fn with_weights(weights: Weights) -> AlphaBeta {
+        AlphaBeta {
+            weights,
+            max_candidates: DEFAULT_MAX_CANDIDATES,
+            threads: 1,
+            table: Arc::new(TranspositionTable::new(DEFAULT_TABLE_SIZE)),
+            difficulty: Difficulty::Master,
+            seed: 0,
+        }
+    }
+
+    /// Set the maximum number of candidate steps searched at each position.
+    /// Fewer candidates allow searching deeper, at the risk of missing good steps.
+    pub fn set_max_candidates(&mut self, max_candidates: usize) {
+        self.max_candidates = max_candidates.max(1);
+    }
+
+    /// Get the number of threads to search with.
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Set the number of threads to search with, which is at least 1.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// Get the number of entries in the transposition table.
+    pub fn table_size(&self) -> usize {
+        self.table.size()
+    }
+
+    /// Set the number of entries in the transposition table, rounded up to a power of two.
+    pub fn set_table_size(&mut self, size: usize) {
+        self.table = Arc::new(TranspositionTable::new(size));
+    }
+
+    /// Get the level of playing strength.
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// Set the level of playing strength.
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+    }
+
+    /// Set the seed of the random choices made below full strength.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    /// Choose a step below full strength, within limits.
+    ///
+    /// Steps that win or stop the opponent from winning on the next step are always considered
+    /// alone. Otherwise, the engine may miss the threats of the opponent and only look at its
+    /// own patterns, or else score each candidate with a search limited by the difficulty.
+    /// The step is then chosen at random, favoring higher scores.
+    /// Return `None` if there are no candidates, such as when every empty point is forbidden.
+    fn search_casual(&mut self, game: &Game, limits: &SearchLimits) -> Option<SearchResult> {
+        let mut rng = Rng::new(self.seed ^ game.position_hash());
+        let side = game.side();
+        let mut candidates = candidates(game, &self.weights);
+        forced_candidates(&mut candidates);
+        candidates.truncate(self.max_candidates);
+
+        let depth = limits.max_depth().min(self.difficulty.max_depth().unwrap_or(MAX_PLY)).max(1);
+        let forced = candidates.first()?.1 >= BLOCKING_STEP;
+        let mut nodes = 0;
+
+        let scores: Vec<(Point, i32)> = if !forced && rng.next_f64() < self.difficulty.miss_chance() {
+            // Blind to the opponent, so only the patterns of the side count
+            let sign = if game.rules().rule_set == RuleSet::Misere { -1 } else { 1 };
+            candidates.iter()
+                .map(|&(point, _)| {
+                    let score: i32 = game.directions().iter()
+                        .filter_map(|&direction| pattern_at(game, &point, direction, side))
+                        .map(|pattern| self.weights.get(pattern))
+                        .sum();
+                    (point, sign * score)
+                })
+                .collect()
+        } else {
+            let mut engine = self.clone();
+            engine.difficulty = Difficulty::Master;
+            let child_limits = SearchLimits { depth: Some(depth - 1), ..*limits };
+
+            candidates.iter()
+                .map(|&(point, _)| {
+                    let mut child = game.clone();
+                    child.add_step(point).expect("candidate steps should be valid");
+                    let score = match child.state() {
+                        GameState::Finished { winner_side, .. } if *winner_side == side => WIN_SCORE - 1,
+                        GameState::Finished { .. } => -(WIN_SCORE - 1),
+                        GameState::BoardFull | GameState::NoWinPossible => 0,
+                        GameState::Normal if depth == 1 => -evaluate(&child, &self.weights),
+                        GameState::Normal => match engine.search(&child, &child_limits) {
+                            Some(result) => {
+                                nodes += result.nodes;
+                                -result.score
+                            },
+                            // The opponent has no step, which loses for them as in `negamax`
+                            None => WIN_SCORE - 1,
+                        },
+                    };
+                    (point, score)
+                })
+                .collect()
+        };
+
+        let (best_step, score) = choose_weighted(&scores, self.difficulty.temperature(), &mut rng);
+        Some(SearchResult {
+            best_step,
+            score,
+            pv: vec![best_step],
+            depth,
+            nodes,
+            visits: vec![],
+        })
+    }
+}
+
+impl Default for AlphaBeta {
+    fn default() -> Self {
+        AlphaBeta::new()
+    }
+}
+
+impl Engine for AlphaBeta {
+    fn search(&mut self, game: &Game, limits: &SearchLimits) -> Option<SearchResult> {
+        if !matches!(game.state(), GameState::Normal) || !is_supported(game) {
+            return None;
+        }
+
+        if self.difficulty != Difficulty::Master {
+            return self.search_casual(game, limits);
+        }
+
+        let clock = SearchClock::start(limits);
+        let total_nodes = AtomicU64::new(0);
+        let stop = AtomicBool::new(false);
+        self.table.clear();
+
+        let new_worker = || Worker {
+            weights: &self.weights,
+            max_candidates: self.max_candidates,
+            table: &self.table,
+            clock: &clock,
+            total_nodes: &total_nodes,
+            stop: &stop,
+            nodes: 0,
+            aborted: false,
+        };
+
+        let result = thread::scope(|scope| {
+            // Helpers start at different depths, so they fill the table ahead of the main thread
+            for i in 1..self.threads {
+                let mut helper = new_worker();
+                let mut game = game.clone();
+                scope.spawn(move || helper.iterate(&mut game, limits.max_depth(), 1 + i % 2));
+            }
+
+            let mut main = new_worker();
+            let result = main.iterate(&mut game.clone(), limits.max_depth(), 1);
+            stop.store(true, Ordering::Relaxed);
+            result
+        });
+
+        let nodes = total_nodes.load(Ordering::Relaxed);
+        match result {
+            Some(mut result) => {
+                result.nodes = nodes;
+                Some(result)
+            },
+            None => {
+                // Out of budget before completing any iteration, so use the best ordered step
+                let (best_step, _) = *candidates(game, &self.weights).first()?;
+                Some(SearchResult {
+                    best_step,
+                    score: evaluate(game, &self.weights),
+                    pv: vec![best_step],
+                    depth: 0,
+                    nodes,
+                    visits: vec![],
+                })
+            },
+        }
+    }
+}
+
+/// Choose one of the scored steps at random, where a step is chosen more often the higher its
+/// score is compared to the others, scaled by temperature. A winning step is always chosen.
+fn choose_weighted(scores: &[(Point, i32)], temperature: f64, rng: &mut Rng) -> (Point, i32) {
+    // There is always at least one candidate
+    let best = *scores.iter().max_by_key(|(_, score)| *score).unwrap();
+    if temperature <= 0.0 || best.1 >= WIN_SCORE - MAX_PLY as i32 {
+        return best;
+    }
+
+    let weights: Vec<f64> = scores.iter()
+        .map(|(_, score)| ((*score as f64 - best.1 as f64) / temperature).exp())
+        .collect();
+    let mut target = rng.next_f64() * weights.iter().sum::<f64>();
+
+    for (scored, weight) in scores.iter().zip(&weights) {
+        if target < *weight {
+            return *scored;
+        }
+        target -= weight;
+    }
+    best
+}
+
+/// The state of one thread of a search.
+struct Worker<'a> {
+    weights: &'a Weights,
+    max_candidates: usize,
+    table: &'a TranspositionTable,
+    clock: &'a SearchClock,
+    /// The number of positions visited by all threads, updated at every check of the limits.
+    total_nodes: &'a AtomicU64,
+    /// Set when the main thread is done, so the other threads must stop.
+    stop: &'a AtomicBool,
+    /// The number of positions visited by this thread.
+    nodes: u64,
+    /// Set when the search ran out of nodes or time, and the current iteration must be discarded.
+    aborted: bool,
+}
+
+impl<'a> Worker<'a> {
+    /// Search the game with iterative deepening from start_depth to max_depth.
+    /// Return the result of the last completed iteration, if any.
+    fn iterate(&mut self, game: &mut Game, max_depth: usize, start_depth: usize) -> Option<SearchResult> {
+        let mut result: Option<SearchResult> = None;
+
+        for depth in start_depth..=max_depth {
+            let prev_pv = result.as_ref().map(|r| r.pv.clone()).unwrap_or_default();
+            let mut pv = vec![];
+            let score = self.negamax(game, depth, 0, -WIN_SCORE - 1, WIN_SCORE + 1, &prev_pv, &mut pv);
+
+            // Keep the result of the last completed iteration,
+            // unless the first iteration did not complete
+            if self.aborted && result.is_some() {
+                break;
+            }
+            if let Some(&best_step) = pv.first() {
+                result = Some(SearchResult {
+                    best_step,
+                    score,
+                    pv,
+                    depth,
+                    nodes: self.nodes,
+                    visits: vec![],
+                });
+            }
+            if self.aborted || score.abs() >= WIN_SCORE - MAX_PLY as i32 {
+                // Stop early when out of budget, or the outcome is already decided
+                break;
+            }
+        }
+
+        // Count the positions visited since the last check
+        self.total_nodes.fetch_add(self.nodes % CHECK_INTERVAL, Ordering::Relaxed);
+        result
+    }
+
+    /// Search the game to depth with negamax and alpha-beta pruning.
+    /// Return the score from the view of the side to move, and fill in the principal variation.
+    ///
+    /// The principal variation of the previous iteration, if any, is searched first,
+    /// followed by the best step stored in the transposition table.
+    #[allow(clippy::too_many_arguments)]
+    fn negamax(
+        &mut self,
+        game: &mut Game,
+        depth: usize,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        prev_pv: &[Point],
+        pv: &mut Vec<Point>,
+    ) -> i32 {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            let total = self.total_nodes.fetch_add(CHECK_INTERVAL, Ordering::Relaxed) + CHECK_INTERVAL;
+            if self.clock.is_out(total) || self.stop.load(Ordering::Relaxed) {
+                self.aborted = true;
+            }
+        }
+        if self.aborted {
+            return 0;
+        }
+
+        match game.state() {
+            // Under misère rules, the last step made a row and lost, so the side to move has won
+            GameState::Finished { winner_side, .. } if *winner_side == game.side() => return WIN_SCORE - ply as i32,
+            // The last step won, so the side to move has lost
+            GameState::Finished { .. } => return -(WIN_SCORE - ply as i32),
+            GameState::BoardFull | GameState::NoWinPossible => return 0,
+            GameState::Normal => (),
+        }
+        if depth == 0 || ply >= MAX_PLY {
+            return evaluate(game, self.weights);
+        }
+
+        let hash = game.position_hash();
+        let entry = self.table.probe(hash, ply);
+        if let Some(entry) = entry.filter(|entry| ply > 0 && entry.depth >= depth) {
+            let cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.score >= beta,
+                Bound::Upper => entry.score <= alpha,
+            };
+            if cutoff {
+                pv.extend(entry.best_step);
+                return entry.score;
+            }
+        }
+
+        let mut candidates = candidates(game, self.weights);
+        forced_candidates(&mut candidates);
+        candidates.truncate(self.max_candidates);
+
+        // Search the best step from the table, then the step from the previous
+        // principal variation first
+        for first in entry.and_then(|entry| entry.best_step).iter().chain(prev_pv.first()) {
+            if let Some(i) = candidates.iter().position(|(p, _)| p == first) {
+                let candidate = candidates.remove(i);
+                candidates.insert(0, candidate);
+            }
+        }
+
+        let original_alpha = alpha;
+        let mut best = -WIN_SCORE - 1;
+        let mut child_pv = vec![];
+
+        for (i, (point, _)) in candidates.into_iter().enumerate() {
+            let child_prev_pv = if i == 0 && prev_pv.first() == Some(&point) {
+                &prev_pv[1..]
+            } else {
+                &[]
+            };
+
+            // Candidates are always empty points on the board
+            game.add_step(point).expect("candidate steps should be valid");
+            child_pv.clear();
+            let score = -self.negamax(game, depth - 1, ply + 1, -beta, -alpha, child_prev_pv, &mut child_pv);
+            game.undo_step();
+
+            if self.aborted {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+                pv.clear();
+                pv.push(point);
+                pv.extend_from_slice(&child_pv);
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best <= original_alpha {
+            Bound::Upper
+        } else if best >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table.store(hash, ply, &TableEntry {
+            depth,
+            score: best,
+            bound,
+            best_step: pv.first().cloned(),
+        });
+
+        best
+    }
+}
@@ -0,0 +1,87 @@
+use std::fmt;
+
+/// A named level of playing strength for an engine.
+///
+/// Lower levels search less deeply, choose among good steps at random with weights based
+/// on their scores, and sometimes miss threats that do not force a reply on the next step.
+/// Threats to win on the next step are never missed.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum Difficulty {
+    Beginner,
+    Easy,
+    Medium,
+    Hard,
+    /// The full strength of the engine, without any randomness.
+    #[default]
+    Master,
+}
+
+impl Difficulty {
+    /// All the levels, from the weakest to the strongest.
+    pub const ALL: [Difficulty; 5] = [
+        Difficulty::Beginner,
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Master,
+    ];
+
+    /// Get the name of the level, in lowercase.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Beginner => "beginner",
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+            Difficulty::Master => "master",
+        }
+    }
+
+    /// Get the level with name, ignoring case, if there is one.
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        Difficulty::ALL.iter()
+            .find(|difficulty| difficulty.name().eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
+    /// Get the maximum depth searched at this level, or `None` if it is not limited.
+    pub fn max_depth(&self) -> Option<usize> {
+        match self {
+            Difficulty::Beginner => Some(1),
+            Difficulty::Easy => Some(2),
+            Difficulty::Medium => Some(3),
+            Difficulty::Hard => Some(4),
+            Difficulty::Master => None,
+        }
+    }
+
+    /// Get the scale of scores when choosing a step at random. Steps whose scores differ by
+    /// this much are chosen e (about 2.7) times as often as each other. Zero means the best
+    /// step is always chosen.
+    pub(crate) fn temperature(&self) -> f64 {
+        match self {
+            Difficulty::Beginner => 600.0,
+            Difficulty::Easy => 250.0,
+            Difficulty::Medium => 80.0,
+            Difficulty::Hard => 15.0,
+            Difficulty::Master => 0.0,
+        }
+    }
+
+    /// Get the chance of missing the threats of the opponent that do not force a reply.
+    pub(crate) fn miss_chance(&self) -> f64 {
+        match self {
+            Difficulty::Beginner => 0.4,
+            Difficulty::Easy => 0.25,
+            Difficulty::Medium => 0.1,
+            Difficulty::Hard => 0.03,
+            Difficulty::Master => 0.0,
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
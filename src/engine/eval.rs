@@ -0,0 +1,402 @@
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::{Game, GameSide, GameSpot, Point, RuleSet};
+
+/// The furthest distance from a point that is looked at when finding patterns through it.
+pub(crate) const MAX_REACH: usize = 16;
+
+/// The score of a candidate step that wins the game.
+static WINNING_STEP: i32 = 1 << 30;
+
+/// The score of a candidate step that stops the opponent from winning on their next step.
+pub(crate) static BLOCKING_STEP: i32 = 1 << 29;
+
+/// A shape of pieces from one side on a line, which could become a win.
+///
+/// A shape is open when there are at least two different ways to complete it into a win,
+/// e.g. `_XXXX_` is an open four and `OXXXX_` is a four.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Pattern {
+    /// Enough consecutive pieces to win.
+    Five,
+    OpenFour,
+    Four,
+    OpenThree,
+    Three,
+    OpenTwo,
+    Two,
+}
+
+impl Pattern {
+    /// All the patterns, from strongest to weakest.
+    pub const ALL: [Pattern; 7] = [
+        Pattern::Five,
+        Pattern::OpenFour,
+        Pattern::Four,
+        Pattern::OpenThree,
+        Pattern::Three,
+        Pattern::OpenTwo,
+        Pattern::Two,
+    ];
+
+    /// Return the index of the pattern in `Pattern::ALL`.
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    /// Return true if the pattern threatens to win on the next step, otherwise false.
+    pub fn is_four(&self) -> bool {
+        matches!(self, Pattern::OpenFour | Pattern::Four)
+    }
+
+    /// Get the name of the pattern, in snake case.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pattern::Five => "five",
+            Pattern::OpenFour => "open_four",
+            Pattern::Four => "four",
+            Pattern::OpenThree => "open_three",
+            Pattern::Three => "three",
+            Pattern::OpenTwo => "open_two",
+            Pattern::Two => "two",
+        }
+    }
+
+    /// Get the pattern with name, if there is one.
+    pub fn from_name(name: &str) -> Option<Pattern> {
+        Pattern::ALL.iter().find(|pattern| pattern.name() == name).cloned()
+    }
+}
+
+/// The weights of each pattern used to evaluate a position.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Weights {
+    /// The weight of each pattern, in the order of `Pattern::ALL`.
+    values: [i32; 7],
+}
+
+impl Weights {
+    /// Create weights with the weight of each pattern, in the order of `Pattern::ALL`.
+    pub fn new(values: [i32; 7]) -> Weights {
+        Weights {
+            values,
+        }
+    }
+
+    /// Get the weight of a pattern.
+    pub fn get(&self, pattern: Pattern) -> i32 {
+        self.values[pattern.index()]
+    }
+
+    /// Set the weight of a pattern.
+    pub fn set(&mut self, pattern: Pattern, value: i32) {
+        self.values[pattern.index()] = value;
+    }
+
+    /// Read weights from text, with one pattern name and its weight on each line, such as
+    /// `open_three 1000`. Empty lines and lines starting with `#` are ignored, and patterns
+    /// that are not given keep their default weights.
+    pub fn from_text(text: &str) -> Result<Weights, WeightsError> {
+        let mut weights = Weights::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let pattern = words.next().and_then(Pattern::from_name);
+            let value = words.next().and_then(|word| word.parse().ok());
+            match (pattern, value, words.next()) {
+                (Some(pattern), Some(value), None) => weights.set(pattern, value),
+                _ => return Err(WeightsError::InvalidLine(i + 1)),
+            }
+        }
+
+        Ok(weights)
+    }
+
+    /// Read weights from the file at path, in the format of `from_text`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Weights, WeightsError> {
+        let text = fs::read_to_string(path).map_err(WeightsError::Io)?;
+        Weights::from_text(&text)
+    }
+
+    /// Write the weights to text, in the format of `from_text`.
+    pub fn to_text(&self) -> String {
+        Pattern::ALL.iter()
+            .map(|pattern| format!("{} {}\n", pattern.name(), self.get(*pattern)))
+            .collect()
+    }
+
+    /// Write the weights to the file at path, in the format of `from_text`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+}
+
+/// An error that is caused by reading invalid weights.
+#[derive(Debug)]
+pub enum WeightsError {
+    /// The file could not be read.
+    Io(io::Error),
+
+    /// The line with the number is not a pattern name followed by a weight.
+    InvalidLine(usize),
+}
+
+impl fmt::Display for WeightsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WeightsError::Io(err) => write!(f, "cannot read weights: {}", err),
+            WeightsError::InvalidLine(line) => write!(f, "invalid weight on line {}", line),
+        }
+    }
+}
+
+impl error::Error for WeightsError {}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights::new([100_000, 10_000, 1_000, 1_000, 150, 100, 10])
+    }
+}
+
+/// Classify the strongest pattern in a segment of a line that contains no opponent pieces,
+/// where `true` is a piece of the side and `false` is an empty spot.
+pub(crate) fn classify_segment(segment: &[bool], connect: usize) -> Option<Pattern> {
+    if segment.len() < connect {
+        return None;
+    }
+
+    // Slide a window of winning length along the segment, and count the windows with the
+    // most pieces. The shape is open if there are multiple such windows.
+    let mut count = segment[..connect].iter().filter(|&&taken| taken).count();
+    let mut best = count;
+    let mut best_windows = 1;
+
+    for i in connect..segment.len() {
+        count = count + segment[i] as usize - segment[i - connect] as usize;
+        if count > best {
+            best = count;
+            best_windows = 1;
+        } else if count == best {
+            best_windows += 1;
+        }
+    }
+
+    let open = best_windows > 1;
+    match (connect - best, open) {
+        (0, _) => Some(Pattern::Five),
+        (1, true) => Some(Pattern::OpenFour),
+        (1, false) => Some(Pattern::Four),
+        (2, true) => Some(Pattern::OpenThree),
+        (2, false) => Some(Pattern::Three),
+        (3, true) => Some(Pattern::OpenTwo),
+        (3, false) => Some(Pattern::Two),
+        _ => None,
+    }
+}
+
+/// Call f with the pattern of every segment of spots that contains no pieces of the
+/// opponent of side, where connect pieces in a row win.
+fn for_each_pattern<F>(spots: &[GameSpot], side: GameSide, connect: usize, mut f: F)
+where
+    F: FnMut(Pattern)
+{
+    let mut segment = Vec::with_capacity(spots.len());
+
+    for spot in spots {
+        match spot {
+            GameSpot::Empty => segment.push(false),
+            GameSpot::Taken(s) if *s == side => segment.push(true),
+            GameSpot::Taken(_) | GameSpot::Blocked => {
+                if let Some(pattern) = classify_segment(&segment, connect) {
+                    f(pattern);
+                }
+                segment.clear();
+            },
+        }
+    }
+
+    if let Some(pattern) = classify_segment(&segment, connect) {
+        f(pattern);
+    }
+}
+
+/// Return the pattern created through point in direction if side were to place a piece there.
+pub(crate) fn pattern_at(game: &Game, point: &Point, direction: (isize, isize, isize), side: GameSide) -> Option<Pattern> {
+    let reach = game.rules().connect - 1;
+    let mut cells = [false; 2 * MAX_REACH + 1];
+    let center = reach;
+    let mut start = center;
+    let mut end = center + 1;
+    cells[center] = true;
+
+    // Extend the segment in both directions until an opponent piece or the edge of the board
+    for &sign in &[-1, 1] {
+        for distance in 1..=reach {
+            let spot = match game.offset(point, direction, sign * distance as isize) {
+                Some(p) => game.spot(&p),
+                None => break,
+            };
+            let taken = match spot {
+                GameSpot::Empty => false,
+                GameSpot::Taken(s) if s == side => true,
+                GameSpot::Taken(_) | GameSpot::Blocked => break,
+            };
+            if sign < 0 {
+                start -= 1;
+                cells[start] = taken;
+            } else {
+                cells[end] = taken;
+                end += 1;
+            }
+        }
+    }
+
+    classify_segment(&cells[start..end], reach + 1)
+}
+
+/// Evaluate the position from the view of the side to move, using weights.
+/// Under misère rules a side is forced toward making a row by its own patterns,
+/// so the score is negated.
+pub fn evaluate(game: &Game, weights: &Weights) -> i32 {
+    let score: i32 = pattern_features(game).iter()
+        .zip(&Pattern::ALL)
+        .map(|(count, pattern)| count * weights.get(*pattern))
+        .sum();
+
+    if game.rules().rule_set == RuleSet::Misere {
+        -score
+    } else {
+        score
+    }
+}
+
+/// Count the patterns on the board from the view of the side to move, in the order of
+/// `Pattern::ALL`. Each count is the number of patterns of the side to move minus the number
+/// of the opponent, where fours of the side to move count as fives, since they can be
+/// completed on this step.
+///
+/// The evaluation of a position is the sum of each count multiplied by the weight of its pattern.
+pub fn pattern_features(game: &Game) -> [i32; 7] {
+    let side = game.side();
+    let mut features = [0; 7];
+    let mut spots = Vec::with_capacity(game.size());
+
+    for line in &game.board.lines {
+        spots.clear();
+        spots.extend(line.iter().map(|p| game.spot(p)));
+
+        for_each_pattern(&spots, side, game.rules().connect, |pattern| {
+            let pattern = if pattern.is_four() { Pattern::Five } else { pattern };
+            features[pattern.index()] += 1;
+        });
+        for_each_pattern(&spots, side.toggle(), game.rules().connect, |pattern| {
+            features[pattern.index()] -= 1;
+        });
+    }
+
+    features
+}
+
+/// Return the empty points worth considering as the next step, each with a score for
+/// ordering, from the best to the worst.
+///
+/// Candidates are the empty points near existing pieces that are not forbidden by the rules,
+/// or the center of the board if there are none. Steps that win have a score of at least
+/// `WINNING_STEP`, and steps that stop the opponent from winning immediately have a score
+/// of at least `BLOCKING_STEP`.
+///
+/// Under misère rules, steps that make a row lose and are ordered last, nothing needs blocking,
+/// and the patterns a step makes or stops count against it.
+pub fn candidates(game: &Game, weights: &Weights) -> Vec<(Point, i32)> {
+    let side = game.side();
+    let misere = game.rules().rule_set == RuleSet::Misere;
+    let size = game.size();
+    let mut candidates = vec![];
+
+    // Mark the points near existing pieces
+    let mut near = vec![false; size * size];
+    for (_, step) in game.iter_steps() {
+        for x in step.x.saturating_sub(2)..=(step.x + 2).min(size - 1) {
+            for y in step.y.saturating_sub(2)..=(step.y + 2).min(size - 1) {
+                near[y * size + x] = true;
+            }
+        }
+    }
+
+    for point in game.iter_points() {
+        if !near[point.y * size + point.x] || !game.spot(point).is_empty() || game.is_forbidden(point, side) {
+            continue;
+        }
+
+        let mut score = 0;
+        let mut wins = false;
+        let mut blocks = false;
+        for &direction in game.directions() {
+            match pattern_at(game, point, direction, side) {
+                Some(Pattern::Five) => wins = true,
+                Some(pattern) => score += weights.get(pattern),
+                None => (),
+            }
+            match pattern_at(game, point, direction, side.toggle()) {
+                Some(Pattern::Five) => blocks = true,
+                // Blocking is slightly less valuable than making the same pattern
+                Some(pattern) => score += weights.get(pattern) * 9 / 10,
+                None => (),
+            }
+        }
+
+        if misere {
+            score = -score;
+            if wins {
+                score -= WINNING_STEP;
+            }
+        } else if wins {
+            score += WINNING_STEP;
+        } else if blocks {
+            score += BLOCKING_STEP;
+        }
+
+        candidates.push((*point, score));
+    }
+
+    if candidates.is_empty() {
+        let center = Point::new(game.size() / 2, game.size() / 2);
+        if game.step_count() == 0 && game.spot(&center).is_empty() {
+            candidates.push((center, 0));
+        } else {
+            // No empty points near pieces, or the center is blocked, so consider every empty point,
+            // closest to the center first
+            candidates.extend(game.iter_points()
+                .filter(|p| game.spot(p).is_empty() && !game.is_forbidden(p, side))
+                .map(|p| {
+                    let distance = (p.x as i32 - center.x as i32).abs().max((p.y as i32 - center.y as i32).abs());
+                    (*p, -distance)
+                })
+            );
+        }
+    }
+
+    candidates.sort_by(|(_, a), (_, b)| b.cmp(a));
+    candidates
+}
+
+/// Reduce ordered candidates to the steps that must be considered: only a winning step if
+/// there is one, or only the blocking steps if the opponent threatens to win.
+pub(crate) fn forced_candidates(candidates: &mut Vec<(Point, i32)>) {
+    match candidates.first() {
+        Some(&(_, score)) if score >= WINNING_STEP => candidates.truncate(1),
+        Some(&(_, score)) if score >= BLOCKING_STEP => {
+            candidates.retain(|&(_, score)| score >= BLOCKING_STEP);
+        },
+        _ => (),
+    }
+}
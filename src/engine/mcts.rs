@@ -0,0 +1,336 @@
+use crate::{Game, GameSide, GameSpot, GameState, Point, Rng, RuleSet};
+use crate::engine::{
+    Engine,
+    SearchClock,
+    SearchLimits,
+    SearchResult,
+    Weights,
+    Pattern,
+    candidates,
+    is_supported,
+    eval::{forced_candidates, pattern_at},
+};
+
+/// The exploration constant used when no other value is given.
+static DEFAULT_EXPLORATION: f64 = 1.4;
+
+/// The number of playouts when no node or time limit is given.
+static DEFAULT_PLAYOUTS: u64 = 2_000;
+
+/// The number of candidate steps expanded at each node of the tree.
+static DEFAULT_MAX_CANDIDATES: usize = 15;
+
+/// The number of times a playout under misère rules tries another point to avoid making a row.
+static MAX_SAFE_TRIES: usize = 8;
+
+/// The score of a position that is certainly won, when converting a win rate to a score.
+static MAX_RATE_SCORE: f64 = 10_000.0;
+
+/// A node in the search tree.
+#[derive(Clone, Debug)]
+struct MctsNode {
+    /// The step leading to this node, or `None` for the root.
+    point: Option<Point>,
+    /// The parent of the node, or `None` for the root.
+    parent: Option<usize>,
+    /// The expanded children of the node.
+    children: Vec<usize>,
+    /// The candidate steps that are not yet expanded, with the best one last.
+    untried: Vec<Point>,
+    /// The number of playouts through this node.
+    visits: u64,
+    /// The total reward of the playouts through this node,
+    /// from the view of the side that played the step leading to it.
+    reward: f64,
+}
+
+impl MctsNode {
+    fn new(point: Option<Point>, parent: Option<usize>, game: &Game, weights: &Weights, max_candidates: usize) -> MctsNode {
+        let untried = match game.state() {
+            GameState::Normal => {
+                let mut candidates = candidates(game, weights);
+                forced_candidates(&mut candidates);
+                candidates.truncate(max_candidates);
+                candidates.into_iter().rev().map(|(p, _)| p).collect()
+            },
+            _ => vec![],
+        };
+
+        MctsNode {
+            point,
+            parent,
+            children: vec![],
+            untried,
+            visits: 0,
+            reward: 0.0,
+        }
+    }
+}
+
+/// An engine using Monte Carlo tree search with the UCT selection rule,
+/// and playouts guided by simple heuristics.
+///
+/// The search is deterministic for a given seed and number of playouts.
+/// Each playout counts as one node for the search limits, and the depth limit is ignored.
+///
+/// The score of a result is the win rate of the best step, scaled from `-10000` for a
+/// certain loss to `10000` for a certain win.
+#[derive(Clone, Debug)]
+pub struct Mcts {
+    /// The weights used to order the candidate steps in the tree.
+    weights: Weights,
+    /// The exploration constant of the UCT rule. Higher values explore more.
+    exploration: f64,
+    /// The maximum number of candidate steps expanded at each node.
+    max_candidates: usize,
+    /// The seed of the random number generator for each search.
+    seed: u64,
+}
+
+impl Mcts {
+    /// Create a new engine with a seed for the random playouts.
+    pub fn new(seed: u64) -> Mcts {
+        Mcts {
+            weights: Weights::default(),
+            exploration: DEFAULT_EXPLORATION,
+            max_candidates: DEFAULT_MAX_CANDIDATES,
+            seed,
+        }
+    }
+
+    /// Set the exploration constant of the UCT rule.
+    pub fn set_exploration(&mut self, exploration: f64) {
+        self.exploration = exploration;
+    }
+
+    /// Set the maximum number of candidate steps expanded at each node.
+    pub fn set_max_candidates(&mut self, max_candidates: usize) {
+        self.max_candidates = max_candidates.max(1);
+    }
+
+    /// Select the child of node with the highest UCT value.
+    fn select_child(&self, nodes: &[MctsNode], node: usize) -> usize {
+        let parent_visits = (nodes[node].visits as f64).ln();
+        let uct = |child: &MctsNode| {
+            child.reward / child.visits as f64
+                + self.exploration * (parent_visits / child.visits as f64).sqrt()
+        };
+
+        nodes[node].children.iter()
+            .copied()
+            .max_by(|&a, &b| uct(&nodes[a]).partial_cmp(&uct(&nodes[b])).unwrap())
+            .unwrap()
+    }
+}
+
+impl Engine for Mcts {
+    fn search(&mut self, game: &Game, limits: &SearchLimits) -> Option<SearchResult> {
+        if !matches!(game.state(), GameState::Normal) || !is_supported(game) {
+            return None;
+        }
+
+        let limits = match limits {
+            SearchLimits { nodes: None, time: None, .. } => SearchLimits::nodes(DEFAULT_PLAYOUTS),
+            _ => *limits,
+        };
+        let clock = SearchClock::start(&limits);
+        let mut rng = Rng::new(self.seed);
+        let mut nodes = vec![MctsNode::new(None, None, game, &self.weights, self.max_candidates)];
+        let mut playouts = 0;
+
+        while playouts == 0 || !clock.is_out(playouts) {
+            let mut game = game.clone();
+            let mut node = 0;
+
+            // Selection
+            while nodes[node].untried.is_empty() && !nodes[node].children.is_empty() {
+                node = self.select_child(&nodes, node);
+                game.add_step(nodes[node].point.unwrap()).expect("tree steps should be valid");
+            }
+
+            // Expansion
+            if let Some(point) = nodes[node].untried.pop() {
+                game.add_step(point).expect("candidate steps should be valid");
+                let child = MctsNode::new(Some(point), Some(node), &game, &self.weights, self.max_candidates);
+                nodes.push(child);
+
+                let child = nodes.len() - 1;
+                nodes[node].children.push(child);
+                node = child;
+            }
+
+            // Simulation
+            let mut side = game.side().toggle();
+            let winner = playout(&mut game, &mut rng);
+
+            // Backpropagation, where the side that played into a node alternates going up
+            let mut current = Some(node);
+            while let Some(n) = current {
+                nodes[n].visits += 1;
+                nodes[n].reward += match winner {
+                    Some(w) if w == side => 1.0,
+                    Some(_) => 0.0,
+                    None => 0.5,
+                };
+                side = side.toggle();
+                current = nodes[n].parent;
+            }
+
+            playouts += 1;
+        }
+
+        let mut visits: Vec<(Point, u64)> = nodes[0].children.iter()
+            .map(|&c| (nodes[c].point.unwrap(), nodes[c].visits))
+            .collect();
+        visits.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+        // Follow the most visited children for the principal variation
+        let mut pv = vec![];
+        let mut node = 0;
+        while let Some(&child) = nodes[node].children.iter().max_by_key(|&&c| nodes[c].visits) {
+            pv.push(nodes[child].point.unwrap());
+            node = child;
+        }
+
+        let best = *nodes[0].children.iter().max_by_key(|&&c| nodes[c].visits)?;
+        let rate = nodes[best].reward / nodes[best].visits as f64;
+
+        Some(SearchResult {
+            best_step: nodes[best].point.unwrap(),
+            score: ((2.0 * rate - 1.0) * MAX_RATE_SCORE).round() as i32,
+            depth: pv.len(),
+            pv,
+            nodes: playouts,
+            visits,
+        })
+    }
+}
+
+/// Play steps until the game ends, and return the winner, or `None` for a draw.
+///
+/// A step that wins is always played, followed by a step that blocks the opponent from
+/// winning. Otherwise, a random empty point next to an existing piece is played.
+///
+/// Under misère rules nothing is played to win or block, and the random steps avoid
+/// making a row while there are other points to try. Points forbidden by the rules are
+/// never played, and the game is a draw if the side to move has no other point.
+fn playout(game: &mut Game, rng: &mut Rng) -> Option<GameSide> {
+    let misere = game.rules().rule_set == RuleSet::Misere;
+    let size = game.size();
+    let mut near = vec![false; size * size];
+    let mut pool = vec![];
+
+    let steps: Vec<Point> = game.steps.clone();
+    for step in &steps {
+        add_neighbors(game, step, &mut near, &mut pool);
+    }
+
+    loop {
+        match game.state() {
+            GameState::Finished { winner_side, .. } => return Some(*winner_side),
+            GameState::BoardFull | GameState::NoWinPossible => return None,
+            GameState::Normal => (),
+        }
+
+        let side = game.side();
+        // With no point the side to move may play, the playout is a draw
+        let point = if misere {
+            safe_point(game, side, rng, &mut pool)
+        } else {
+            let allowed = |point: &Point| !game.is_forbidden(point, side);
+            threat_point(game, side).filter(allowed)
+                .or_else(|| threat_point(game, side.toggle()).filter(allowed))
+                .or_else(|| random_point(game, side, rng, &mut pool))
+        }?;
+
+        game.add_step(point).expect("playout steps should be valid");
+        add_neighbors(game, &point, &mut near, &mut pool);
+    }
+}
+
+/// Add the empty points next to point that are not yet marked as near to the pool.
+fn add_neighbors(game: &Game, point: &Point, near: &mut [bool], pool: &mut Vec<Point>) {
+    let size = game.size();
+
+    for x in point.x.saturating_sub(1)..=(point.x + 1).min(size - 1) {
+        for y in point.y.saturating_sub(1)..=(point.y + 1).min(size - 1) {
+            let neighbor = Point::new(x, y);
+            if !near[y * size + x] && game.spot(&neighbor).is_empty() {
+                near[y * size + x] = true;
+                pool.push(neighbor);
+            }
+        }
+    }
+}
+
+/// Take a random empty point from the pool that is not forbidden for side, or any such point
+/// if there are none left in the pool. Return `None` if every empty point is forbidden.
+fn random_point(game: &Game, side: GameSide, rng: &mut Rng, pool: &mut Vec<Point>) -> Option<Point> {
+    let mut forbidden = vec![];
+    let mut found = None;
+    while !pool.is_empty() {
+        let point = pool.swap_remove(rng.below(pool.len()));
+        if !game.spot(&point).is_empty() {
+            continue;
+        }
+        if game.is_forbidden(&point, side) {
+            forbidden.push(point);
+            continue;
+        }
+        found = Some(point);
+        break;
+    }
+    // Points forbidden now may be played later, or by the other side
+    pool.extend(forbidden);
+
+    found.or_else(|| {
+        let empty: Vec<&Point> = game.iter_points()
+            .filter(|p| game.spot(p).is_empty() && !game.is_forbidden(p, side))
+            .collect();
+        rng.choose(&empty).map(|p| **p)
+    })
+}
+
+/// Take a random point as `random_point` does, trying again a few times while side
+/// would make a row there.
+fn safe_point(game: &Game, side: GameSide, rng: &mut Rng, pool: &mut Vec<Point>) -> Option<Point> {
+    let mut point = random_point(game, side, rng, pool)?;
+    for _ in 0..MAX_SAFE_TRIES {
+        if !game.is_winning_step(&point, side) {
+            break;
+        }
+        // The point is still empty, and may be safe for the opponent
+        pool.push(point);
+        point = random_point(game, side, rng, pool)?;
+    }
+    Some(point)
+}
+
+/// Return a point where side would win, looking only at the lines through the last two steps.
+///
+/// During a playout, any winning point is either played or blocked right away,
+/// so a new one can only appear through one of the last two steps.
+fn threat_point(game: &Game, side: GameSide) -> Option<Point> {
+    let reach = game.rules().connect as isize - 1;
+
+    for step in game.steps.iter().rev().take(2) {
+        if game.spot(step) != GameSpot::Taken(side) {
+            continue;
+        }
+        for &direction in game.directions() {
+            for distance in -reach..=reach {
+                let point = match game.offset(step, direction, distance) {
+                    Some(point) => point,
+                    None => continue,
+                };
+                if game.spot(&point).is_empty()
+                    && pattern_at(game, &point, direction, side) == Some(Pattern::Five)
+                {
+                    return Some(point);
+                }
+            }
+        }
+    }
+
+    None
+}
@@ -0,0 +1,140 @@
+//! Built-in engines that choose steps for a game.
+
+mod alphabeta;
+mod difficulty;
+mod eval;
+mod mcts;
+mod tt;
+
+pub use alphabeta::*;
+pub use difficulty::*;
+pub use eval::*;
+pub use mcts::*;
+
+use std::time::{Duration, Instant};
+use crate::{BoardGeometry, Game, Point};
+
+/// The score of a position won by the side to move, before adjusting for the number of steps
+/// needed to win. Scores within `MAX_PLY` of this value represent a forced win.
+pub const WIN_SCORE: i32 = 1_000_000;
+
+/// The maximum number of steps an engine searches ahead.
+pub const MAX_PLY: usize = 128;
+
+/// The depth searched when no other limit is given.
+static DEFAULT_DEPTH: usize = 4;
+
+/// An engine that searches a game for the best step of the side to move.
+pub trait Engine {
+    /// Search the game within the limits, and return the result,
+    /// or `None` if the game is not ongoing or not supported, as decided by `is_supported`,
+    /// or if the side to move has no step, such as when every empty point is forbidden.
+    fn search(&mut self, game: &Game, limits: &SearchLimits) -> Option<SearchResult>;
+}
+
+/// Return true if the built-in engines and solvers can search game, otherwise false.
+/// They need two players who place one stone in every turn, on a board with a single layer,
+/// and rows of 3 to 17 pieces to win.
+pub fn is_supported(game: &Game) -> bool {
+    game.players() == 2
+        && game.rules().has_single_stone_turns()
+        && game.geometry() != BoardGeometry::Cubic
+        && (3..=MAX_REACH + 1).contains(&game.rules().connect)
+}
+
+/// The limits of a search. The search stops when any of the given limits is reached.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct SearchLimits {
+    /// The maximum number of steps to search ahead.
+    pub depth: Option<usize>,
+    /// The maximum number of positions to visit.
+    pub nodes: Option<u64>,
+    /// The maximum time to search for.
+    pub time: Option<Duration>,
+}
+
+impl SearchLimits {
+    /// Create limits that only limit the depth of the search.
+    pub fn depth(depth: usize) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            nodes: None,
+            time: None,
+        }
+    }
+
+    /// Create limits that only limit the number of positions visited.
+    pub fn nodes(nodes: u64) -> SearchLimits {
+        SearchLimits {
+            depth: None,
+            nodes: Some(nodes),
+            time: None,
+        }
+    }
+
+    /// Create limits that only limit the time of the search.
+    pub fn time(time: Duration) -> SearchLimits {
+        SearchLimits {
+            depth: None,
+            nodes: None,
+            time: Some(time),
+        }
+    }
+
+    /// Return the maximum depth to search, which is never greater than `MAX_PLY`.
+    /// If no limit is given at all, the search is limited to a default depth.
+    pub fn max_depth(&self) -> usize {
+        match (self.depth, self.nodes, self.time) {
+            (Some(depth), _, _) => depth.min(MAX_PLY),
+            (None, None, None) => DEFAULT_DEPTH,
+            _ => MAX_PLY,
+        }
+    }
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits::depth(DEFAULT_DEPTH)
+    }
+}
+
+/// The result of a search.
+#[derive(PartialEq, Clone, Debug)]
+pub struct SearchResult {
+    /// The best step found for the side to move.
+    pub best_step: Point,
+    /// The score of the position from the view of the side to move.
+    pub score: i32,
+    /// The principal variation, which is the expected line of play starting with `best_step`.
+    pub pv: Vec<Point>,
+    /// The depth of the last completed iteration of the search.
+    pub depth: usize,
+    /// The number of positions visited.
+    pub nodes: u64,
+    /// The number of times each candidate step was visited, from the most to the least visited,
+    /// for engines that sample steps. Empty for other engines.
+    pub visits: Vec<(Point, u64)>,
+}
+
+/// Keeps track of the node and time limits while searching.
+#[derive(Clone, Debug)]
+pub(crate) struct SearchClock {
+    start: Instant,
+    limits: SearchLimits,
+}
+
+impl SearchClock {
+    /// Start the clock for a search with limits.
+    pub(crate) fn start(limits: &SearchLimits) -> SearchClock {
+        SearchClock {
+            start: Instant::now(),
+            limits: *limits,
+        }
+    }
+
+    /// Return true if the search should stop after visiting nodes, otherwise false.
+    pub(crate) fn is_out(&self, nodes: u64) -> bool {
+        self.limits.nodes.is_some_and(|limit| nodes >= limit)
+            || self.limits.time.is_some_and(|limit| self.start.elapsed() >= limit)
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::Point;
+use crate::engine::{WIN_SCORE, MAX_PLY};
+
+/// The number of entries in a table when no other value is given.
+pub(crate) static DEFAULT_TABLE_SIZE: usize = 1 << 18;
+
+/// The value stored in the step fields of an entry without a best step.
+static NO_STEP: u64 = 0xff;
+
+/// How the score of an entry relates to the true score of the position.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub(crate) enum Bound {
+    /// The score is exact.
+    Exact,
+    /// The true score is at least the score.
+    Lower,
+    /// The true score is at most the score.
+    Upper,
+}
+
+/// The result of searching a position, as stored in a transposition table.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub(crate) struct TableEntry {
+    pub(crate) depth: usize,
+    pub(crate) score: i32,
+    pub(crate) bound: Bound,
+    pub(crate) best_step: Option<Point>,
+}
+
+impl TableEntry {
+    /// Pack the entry into 64 bits, with a win score stored relative to the position at ply.
+    fn pack(&self, ply: usize) -> u64 {
+        let score = score_to_table(self.score, ply) as u32 as u64;
+        let depth = self.depth.min(0xff) as u64;
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+        let (x, y) = match self.best_step {
+            // Steps outside the range of the fields are not stored
+            Some(p) if (p.x as u64) < NO_STEP && (p.y as u64) < NO_STEP => (p.x as u64, p.y as u64),
+            _ => (NO_STEP, NO_STEP),
+        };
+
+        score | depth << 32 | bound << 40 | x << 42 | y << 50
+    }
+
+    /// Unpack an entry from 64 bits, with a win score relative to the position at ply.
+    fn unpack(data: u64, ply: usize) -> TableEntry {
+        let bound = match (data >> 40) & 0b11 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+        let (x, y) = ((data >> 42) & 0xff, (data >> 50) & 0xff);
+
+        TableEntry {
+            depth: ((data >> 32) & 0xff) as usize,
+            score: score_from_table(data as u32 as i32, ply),
+            bound,
+            best_step: if x == NO_STEP { None } else { Some(Point::new(x as usize, y as usize)) },
+        }
+    }
+}
+
+/// A transposition table shared by the threads of a search without locking.
+///
+/// Each entry is stored as two atomic words, the key mixed with the data and the data itself,
+/// so an entry torn by a write from another thread fails the key check and is ignored.
+#[derive(Debug)]
+pub(crate) struct TranspositionTable {
+    entries: Vec<(AtomicU64, AtomicU64)>,
+}
+
+impl TranspositionTable {
+    /// Create a new table with at least size entries, rounded up to a power of two.
+    pub(crate) fn new(size: usize) -> TranspositionTable {
+        let size = size.max(1).next_power_of_two();
+        TranspositionTable {
+            entries: (0..size).map(|_| (AtomicU64::new(0), AtomicU64::new(0))).collect(),
+        }
+    }
+
+    /// Get the number of entries in the table.
+    pub(crate) fn size(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Remove all entries from the table.
+    pub(crate) fn clear(&self) {
+        for (key, data) in &self.entries {
+            key.store(0, Ordering::Relaxed);
+            data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Get the entry for the position with hash, searched at ply.
+    pub(crate) fn probe(&self, hash: u64, ply: usize) -> Option<TableEntry> {
+        let (key, data) = &self.entries[self.index(hash)];
+        let data = data.load(Ordering::Relaxed);
+
+        if key.load(Ordering::Relaxed) ^ data == hash && data != 0 {
+            Some(TableEntry::unpack(data, ply))
+        } else {
+            None
+        }
+    }
+
+    /// Store the entry for the position with hash, searched at ply, replacing any entry in its slot.
+    pub(crate) fn store(&self, hash: u64, ply: usize, entry: &TableEntry) {
+        let (key, data) = &self.entries[self.index(hash)];
+        let packed = entry.pack(ply);
+
+        key.store(hash ^ packed, Ordering::Relaxed);
+        data.store(packed, Ordering::Relaxed);
+    }
+
+    /// Return the index of the slot for hash.
+    fn index(&self, hash: u64) -> usize {
+        (hash as usize) & (self.entries.len() - 1)
+    }
+}
+
+/// Convert a score at ply to a score stored in the table. Win scores count the distance to
+/// the win from the root, so they are stored as the distance from the position instead.
+fn score_to_table(score: i32, ply: usize) -> i32 {
+    if score >= WIN_SCORE - MAX_PLY as i32 {
+        score + ply as i32
+    } else if score <= -(WIN_SCORE - MAX_PLY as i32) {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+/// Convert a score stored in the table to a score at ply.
+fn score_from_table(score: i32, ply: usize) -> i32 {
+    if score >= WIN_SCORE - MAX_PLY as i32 {
+        score - ply as i32
+    } else if score <= -(WIN_SCORE - MAX_PLY as i32) {
+        score + ply as i32
+    } else {
+        score
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{Game, Symmetry};
+
+/// The directions of lines through a point on a square board, as steps in x, y and z.
+static SQUARE_DIRECTIONS: [(isize, isize, isize); 4] = [(1, 0, 0), (0, 1, 0), (1, 1, 0), (1, -1, 0)];
+
+/// The directions of lines through a point on a hex board, as steps in x, y and z.
+static HEX_DIRECTIONS: [(isize, isize, isize); 3] = [(1, 0, 0), (0, 1, 0), (1, -1, 0)];
+
+/// The directions of lines through a point on a cubic board, as steps in x, y and z.
+/// The first four are the directions within a layer, as on a square board.
+static CUBIC_DIRECTIONS: [(isize, isize, isize); 13] = [
+    (1, 0, 0), (0, 1, 0), (1, 1, 0), (1, -1, 0),
+    (0, 0, 1), (1, 0, 1), (1, 0, -1), (0, 1, 1), (0, 1, -1),
+    (1, 1, 1), (1, 1, -1), (1, -1, 1), (1, -1, -1),
+];
+
+/// The symmetries of a hex board, which keep its three line directions.
+static HEX_SYMMETRIES: [Symmetry; 4] = [
+    Symmetry::Identity,
+    Symmetry::Rotate180,
+    Symmetry::Transpose,
+    Symmetry::AntiTranspose,
+];
+
+/// The shape of the cells of a board, which decides the lines a row can be made on.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum BoardGeometry {
+    /// Square cells, where lines run horizontally, vertically and along both diagonals.
+    Square,
+
+    /// Hexagonal cells, laid out as a rhombus of size by size cells. Each row is shifted
+    /// half a cell to the right of the row above, so the cell at `(x, y)` touches
+    /// `(x + 1, y)`, `(x, y + 1)` and `(x + 1, y - 1)` and their opposites.
+    /// Lines run horizontally and along these two diagonals.
+    Hex,
+
+    /// A cubic lattice of size by size by size points, made of size square layers stacked
+    /// along z. Lines run in all 13 directions between neighbouring points, including
+    /// across the layers and along the diagonals of the cube.
+    Cubic,
+}
+
+impl BoardGeometry {
+    /// All the geometries.
+    pub const ALL: [BoardGeometry; 3] = [BoardGeometry::Square, BoardGeometry::Hex, BoardGeometry::Cubic];
+
+    /// Get the name of the geometry, as used in settings.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BoardGeometry::Square => "square",
+            BoardGeometry::Hex => "hex",
+            BoardGeometry::Cubic => "cubic",
+        }
+    }
+
+    /// Get the geometry with a name, if any.
+    pub fn from_name(name: &str) -> Option<BoardGeometry> {
+        BoardGeometry::ALL.iter().find(|geometry| geometry.name() == name).copied()
+    }
+
+    /// Get the directions of lines through a point, as steps in x, y and z.
+    /// Each line is given once, with the opposite direction left out.
+    pub fn directions(&self) -> &'static [(isize, isize, isize)] {
+        match self {
+            BoardGeometry::Square => &SQUARE_DIRECTIONS,
+            BoardGeometry::Hex => &HEX_DIRECTIONS,
+            BoardGeometry::Cubic => &CUBIC_DIRECTIONS,
+        }
+    }
+
+    /// Get the symmetries of a board with the geometry, starting with the identity.
+    /// A cubic board has more symmetries, but only those that keep each layer are given.
+    pub fn symmetries(&self) -> &'static [Symmetry] {
+        match self {
+            BoardGeometry::Square | BoardGeometry::Cubic => &Symmetry::ALL,
+            BoardGeometry::Hex => &HEX_SYMMETRIES,
+        }
+    }
+
+    /// Get the number of layers of a board with the geometry and size.
+    pub fn layers(&self, size: usize) -> usize {
+        match self {
+            BoardGeometry::Cubic => size,
+            _ => 1,
+        }
+    }
+}
+
+// Geometry
+impl Game {
+    /// Get the geometry of the board.
+    pub fn geometry(&self) -> BoardGeometry {
+        self.rules.geometry
+    }
+
+    /// Get the directions of lines through a point on the board.
+    pub(crate) fn directions(&self) -> &'static [(isize, isize, isize)] {
+        self.rules.geometry.directions()
+    }
+}
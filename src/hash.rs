@@ -0,0 +1,107 @@
+use crate::{Game, GameSide, Point};
+use crate::rng::mix;
+
+/// Return the key of a piece from side at point, which is combined into the hash of a game.
+pub(crate) fn piece_key(point: &Point, side: GameSide) -> u64 {
+    // Further players only change the high bits, so the keys of black and white stay the same.
+    // Layers are 0 except on cubic boards, whose sizes are small enough not to overlap the rows
+    let side = side.index() as u64;
+    let layer = (point.z as u64) << 17;
+    mix(((point.x as u64) << 33) ^ ((point.y as u64) << 1) ^ layer ^ (side & 1) ^ ((side >> 1) << 56) ^ 0x5eed)
+}
+
+/// Return the hash of an empty board with size, so boards of different sizes hash differently.
+pub(crate) fn empty_hash(size: usize) -> u64 {
+    mix(size as u64)
+}
+
+/// One of the eight symmetries of a square board, made of rotations and reflections.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Symmetry {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipX,
+    FlipY,
+    /// Reflection over the diagonal from the top left to the bottom right.
+    Transpose,
+    /// Reflection over the diagonal from the top right to the bottom left.
+    AntiTranspose,
+}
+
+impl Symmetry {
+    /// All the symmetries, starting with the identity.
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::Identity,
+        Symmetry::Rotate90,
+        Symmetry::Rotate180,
+        Symmetry::Rotate270,
+        Symmetry::FlipX,
+        Symmetry::FlipY,
+        Symmetry::Transpose,
+        Symmetry::AntiTranspose,
+    ];
+
+    /// Apply the symmetry to a point on a board with size.
+    pub fn apply(&self, point: &Point, size: usize) -> Point {
+        let (x, y, z) = (point.x, point.y, point.z);
+        let last = size - 1;
+
+        // The layer of a point on a cubic board stays the same
+        match self {
+            Symmetry::Identity => Point::with_z(x, y, z),
+            Symmetry::Rotate90 => Point::with_z(last - y, x, z),
+            Symmetry::Rotate180 => Point::with_z(last - x, last - y, z),
+            Symmetry::Rotate270 => Point::with_z(y, last - x, z),
+            Symmetry::FlipX => Point::with_z(last - x, y, z),
+            Symmetry::FlipY => Point::with_z(x, last - y, z),
+            Symmetry::Transpose => Point::with_z(y, x, z),
+            Symmetry::AntiTranspose => Point::with_z(last - y, last - x, z),
+        }
+    }
+
+    /// Return the symmetry that undoes this symmetry.
+    pub fn inverse(&self) -> Symmetry {
+        match self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            symmetry => *symmetry,
+        }
+    }
+}
+
+// Hashing
+impl Game {
+    /// Get the hash of the pieces on the board.
+    ///
+    /// Games with the same pieces have the same hash, regardless of the order of the steps.
+    pub fn position_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Return the hash of the pieces on the board after applying a symmetry.
+    pub fn symmetric_hash(&self, symmetry: Symmetry) -> u64 {
+        let size = self.size();
+        self.iter_steps().fold(empty_hash(size), |hash, (side, point)| {
+            hash ^ piece_key(&symmetry.apply(point, size), *side)
+        })
+    }
+
+    /// Return the canonical hash of the position, which is the same for all positions that
+    /// are symmetric to each other, along with every symmetry that maps the position to
+    /// its canonical form.
+    pub fn canonical_hash(&self) -> (u64, Vec<Symmetry>) {
+        let hashes: Vec<(Symmetry, u64)> = self.geometry().symmetries().iter()
+            .map(|&symmetry| (symmetry, self.symmetric_hash(symmetry)))
+            .collect();
+        // There is always at least one symmetry
+        let min = hashes.iter().map(|(_, hash)| *hash).min().unwrap();
+        let symmetries = hashes.into_iter()
+            .filter(|(_, hash)| *hash == min)
+            .map(|(symmetry, _)| symmetry)
+            .collect();
+
+        (min, symmetries)
+    }
+}
@@ -1,32 +1,92 @@
+pub mod engine;
+pub mod protocol;
+pub mod solver;
+mod book;
+mod dark;
+mod geometry;
+mod hash;
+mod obstacles;
+mod opening;
+mod record;
+mod rng;
+mod rules;
+mod tree;
+mod unbounded;
+
+use std::convert::TryFrom;
 use std::error;
 use std::fmt;
 
+pub use book::*;
+pub use dark::*;
+pub use geometry::*;
+pub use hash::*;
+pub use obstacles::*;
+pub use opening::*;
+pub use record::*;
+pub use rng::Rng;
+pub use rules::*;
+pub use tree::*;
+pub use unbounded::*;
+
 /// A point on a game board.
+/// The z coordinate is the layer of a cubic board, and always 0 on other boards.
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub struct Point {
     pub x: usize,
     pub y: usize,
+    pub z: usize,
 }
 
 impl Point {
     pub fn new(x: usize, y: usize) -> Point {
-        Point { x, y }
+        Point { x, y, z: 0 }
+    }
+
+    /// Create a point on the layer z of a cubic board.
+    pub fn with_z(x: usize, y: usize, z: usize) -> Point {
+        Point { x, y, z }
     }
 }
 
-/// The side of a player in a game, either black or white.
-#[derive(PartialEq, Clone, Copy, Debug)]
+/// The side of a player in a game, either black or white,
+/// or one of the further players in a game with more than two players.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum GameSide {
     Black,
     White,
+    /// A player after black and white, numbered from 2. Created with `GameSide::from_index`,
+    /// so `Player(0)` and `Player(1)` are never used.
+    Player(u8),
 }
 
 impl GameSide {
-    /// Return the opposite of the side.
+    /// Create a side from its number, where 0 is black, 1 is white and the rest are further players.
+    /// Return `None` if the number is too large.
+    pub fn from_index(index: usize) -> Option<GameSide> {
+        match index {
+            0 => Some(GameSide::Black),
+            1 => Some(GameSide::White),
+            _ => u8::try_from(index).ok().map(GameSide::Player),
+        }
+    }
+
+    /// Get the number of the side, where 0 is black, 1 is white and the rest are further players.
+    pub fn index(&self) -> usize {
+        match self {
+            GameSide::Black => 0,
+            GameSide::White => 1,
+            GameSide::Player(index) => *index as usize,
+        }
+    }
+
+    /// Return the opposite of the side in a two-player game.
+    /// Further players have no single opposite, so they are returned unchanged.
     fn toggle(&self) -> GameSide {
         match self {
             GameSide::Black => GameSide::White,
             GameSide::White => GameSide::Black,
+            side => *side,
         }
     }
 }
@@ -39,15 +99,15 @@ pub enum GameSpot {
 
     /// A spot on the board taken by a piece from a side.
     Taken(GameSide),
+
+    /// A spot on the board that neither side may use, which breaks any row through it.
+    Blocked,
 }
 
 impl GameSpot {
     /// Return true if the spot is empty, otherwise false.
     pub fn is_empty(&self) -> bool {
-        match self {
-            GameSpot::Empty => true,
-            _ => false,
-        }
+        matches!(self, GameSpot::Empty)
     }
 }
 
@@ -59,6 +119,15 @@ pub enum GameStepError {
 
     /// The point is already taken by a piece on the board.
     PointTaken,
+
+    /// The point is blocked on the board, so no side may use it.
+    Blocked,
+
+    /// The point is forbidden for the side by the rules of the game.
+    Forbidden,
+
+    /// The number of points does not match the stones left to place in the turn.
+    InvalidTurn,
 }
 
 impl fmt::Display for GameStepError {
@@ -66,6 +135,9 @@ impl fmt::Display for GameStepError {
         match self {
             GameStepError::InvalidPoint => write!(f, "invalid point on the board"),
             GameStepError::PointTaken => write!(f, "point is already taken on the board"),
+            GameStepError::Blocked => write!(f, "point is blocked on the board"),
+            GameStepError::Forbidden => write!(f, "point is forbidden by the rules"),
+            GameStepError::InvalidTurn => write!(f, "number of points does not match the turn"),
         }
     }
 }
@@ -73,7 +145,7 @@ impl fmt::Display for GameStepError {
 impl error::Error for GameStepError {}
 
 /// The state of a game.
-#[derive(Debug)]
+#[derive(PartialEq, Clone, Debug)]
 pub enum GameState {
     /// The state representing that the game is still ongoing.
     Normal,
@@ -82,22 +154,33 @@ pub enum GameState {
     Finished {
         /// The side of the winner.
         winner_side: GameSide,
-        /// The consecutive points from the winner that resulted a victory.
-        points: Vec<Point>,
+        /// The side that lost by making the rows under misère rules, in which case the winner is
+        /// the side after it in turn order, or `None` if the winner made the rows.
+        loser_side: Option<GameSide>,
+        /// Every row of consecutive points that ended the game, such as two rows made by
+        /// the same step, in the order of the lines of the board.
+        /// Each row holds all of its points, even if it is longer than needed to win.
+        lines: Vec<Vec<Point>>,
     },
 
     /// The state representing that the game board is full, resulting in a tie.
     BoardFull,
+
+    /// The state representing that neither side can complete a winning row on any line,
+    /// resulting in a tie. Only reached if the rules have `early_draw` set.
+    NoWinPossible,
 }
 
 /// The board state of a game.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct GameBoard {
     /// The size of the board, representing both the width and height.
     size: usize,
-    /// A vector of length size, containing vectors of length size that contains spots.
-    /// A specific spot at `Point(x, y)` is accessed by `spots[x][y]`
-    spots: Vec<Vec<GameSpot>>,
+    /// The number of layers of the board, which is size on a cubic board and 1 otherwise.
+    layers: usize,
+    /// A vector containing the spots of every layer, row by row.
+    /// A specific spot at `Point(x, y, z)` is accessed by `spots[(z * size + y) * size + x]`
+    spots: Vec<GameSpot>,
     /// A vector containing all the points on the game board.
     points: Vec<Point>,
     /// Cache of possible lines where consecutive pieces may result in victory.
@@ -105,27 +188,33 @@ struct GameBoard {
 }
 
 impl GameBoard {
-    /// Create a new board with size, filled with empty spots.
-    fn new(size: usize) -> GameBoard {
-        let spots = vec![vec![GameSpot::Empty; size]; size];
-        let lines = GameBoard::init_lines(size);
-        let points = (0..size)
-            .flat_map(|y| (0..size).map(move |x| Point::new(x, y)))
+    /// Create a new board with size and geometry, filled with empty spots.
+    fn new(size: usize, geometry: BoardGeometry) -> GameBoard {
+        let layers = geometry.layers(size);
+        let spots = vec![GameSpot::Empty; size * size * layers];
+        let lines = GameBoard::init_lines(size, geometry);
+        let points = (0..layers)
+            .flat_map(|z| (0..size).flat_map(move |y| (0..size).map(move |x| Point::with_z(x, y, z))))
             .collect();
 
         GameBoard {
             size,
+            layers,
             spots,
             lines,
             points,
         }
     }
 
-    /// Initialize lines given board size.
+    /// Initialize lines given board size and geometry.
     ///
     /// Create a vector of all possible straight lines, consisting of consecutive points,
-    /// inside a board with given size.
-    fn init_lines(size: usize) -> Vec<Vec<Point>> {
+    /// inside a board with given size. A hex board has no slope down lines.
+    fn init_lines(size: usize, geometry: BoardGeometry) -> Vec<Vec<Point>> {
+        if geometry == BoardGeometry::Cubic {
+            return GameBoard::init_cubic_lines(size);
+        }
+
         let mut lines: Vec<Vec<Point>> = vec![];
 
         for i in 0..size {
@@ -136,9 +225,11 @@ impl GameBoard {
             lines.push((0..size).map(|j| Point::new(j, i)).collect());
 
             // Slope down lines
-            lines.push((0..size - i).map(|j| Point::new(i + j, j)).collect());
-            if i > 0 {
-                lines.push((0..size - i).map(|j| Point::new(j, i + j)).collect());
+            if geometry == BoardGeometry::Square {
+                lines.push((0..size - i).map(|j| Point::new(i + j, j)).collect());
+                if i > 0 {
+                    lines.push((0..size - i).map(|j| Point::new(j, i + j)).collect());
+                }
             }
 
             // Slope up lines
@@ -151,31 +242,68 @@ impl GameBoard {
         lines
     }
 
+    /// Initialize lines of a cubic board given its size.
+    ///
+    /// Each line starts at a point where the previous point in its direction is outside
+    /// the board, and continues until the edge of the board.
+    fn init_cubic_lines(size: usize) -> Vec<Vec<Point>> {
+        let range = 0..size as isize;
+        let mut lines = vec![];
+
+        for &(dx, dy, dz) in BoardGeometry::Cubic.directions() {
+            for z in range.clone() {
+                for y in range.clone() {
+                    for x in range.clone() {
+                        let before = (x - dx, y - dy, z - dz);
+                        if range.contains(&before.0) && range.contains(&before.1) && range.contains(&before.2) {
+                            continue;
+                        }
+
+                        let line = (0..)
+                            .map(|i| (x + dx * i, y + dy * i, z + dz * i))
+                            .take_while(|(x, y, z)| range.contains(x) && range.contains(y) && range.contains(z))
+                            .map(|(x, y, z)| Point::with_z(x as usize, y as usize, z as usize))
+                            .collect();
+                        lines.push(line);
+                    }
+                }
+            }
+        }
+
+        lines
+    }
+
     /// Return true if the point is within bounds of the board, otherwise false.
     fn is_valid(&self, point: &Point) -> bool {
         let range = 0..self.size;
-        range.contains(&point.x) && range.contains(&point.y)
+        range.contains(&point.x) && range.contains(&point.y) && point.z < self.layers
     }
 
     /// Get the spot at point on the board.
     fn get_spot(&self, point: &Point) -> GameSpot {
-        self.spots[point.x][point.y]
+        self.spots[self.index(point)]
     }
 
     /// Set the spot at point on the board.
     fn set_spot(&mut self, point: &Point, spot: GameSpot) {
-        self.spots[point.x][point.y] = spot;
+        let index = self.index(point);
+        self.spots[index] = spot;
+    }
+
+    /// Return the index of point in the spots.
+    fn index(&self, point: &Point) -> usize {
+        (point.z * self.size + point.y) * self.size + point.x
     }
 
     /// Return true if the all spots on the board are taken, otherwise false.
     fn is_full(&self) -> bool {
         // Return false if there is an empty spot
-        !self.spots.iter().any(|vec| vec.iter().any(GameSpot::is_empty))
+        !self.spots.iter().any(GameSpot::is_empty)
     }
 }
 
 /// A Connect 5 game.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Game {
     /// The board of the game.
     board: GameBoard,
@@ -186,31 +314,74 @@ pub struct Game {
     side: GameSide,
     /// The state of the game.
     state: GameState,
+    /// The hash of the pieces on the board, updated when a step is added/removed.
+    hash: u64,
+    /// The rules the game is played under.
+    rules: GameRules,
+    /// The sides in the order they take turns, starting with the side of the first step.
+    turn_order: Vec<GameSide>,
 }
 
 // Initializers
 impl Game {
-    /// Create a new game from size.
+    /// Create a new game from size, played under the default rules.
     pub fn new(size: usize) -> Game {
-        let board = GameBoard::new(size);
+        Game::with_rules(size, GameRules::default())
+    }
+
+    /// Create a new game from size, played under rules.
+    pub fn with_rules(size: usize, rules: GameRules) -> Game {
+        Game::with_players(size, rules, 2).expect("two players are always valid")
+    }
+
+    /// Create a new game from size, played under rules by a number of players,
+    /// who take turns in the order of their numbers, starting with black.
+    /// Return `None` if there are fewer than two players or too many to number.
+    pub fn with_players(size: usize, rules: GameRules, players: usize) -> Option<Game> {
+        let turn_order = (0..players)
+            .map(GameSide::from_index)
+            .collect::<Option<Vec<GameSide>>>()?;
+
+        Game::with_turn_order(size, rules, &turn_order)
+    }
+
+    /// Create a new game from size, played under rules by the sides taking turns in turn_order.
+    /// Return `None` if there are fewer than two sides, or a side appears more than once.
+    pub fn with_turn_order(size: usize, rules: GameRules, turn_order: &[GameSide]) -> Option<Game> {
+        let distinct = turn_order.iter()
+            .enumerate()
+            .all(|(i, side)| !turn_order[..i].contains(side));
+        if turn_order.len() < 2 || !distinct {
+            return None;
+        }
+
+        let board = GameBoard::new(size, rules.geometry);
         let steps = vec![];
-        let side = GameSide::Black;
+        let side = turn_order[0];
 
-        Game {
+        Some(Game {
             board,
             steps,
             side,
             state: GameState::Normal,
-        }
+            hash: hash::empty_hash(size),
+            rules,
+            turn_order: turn_order.to_vec(),
+        })
     }
 
     /// Create a game from given size and steps.
     pub fn from_steps(size: usize, steps: &[Point]) -> Result<Game, GameStepError> {
-        let mut game = Game::new(size);
+        Game::from_steps_with_rules(size, GameRules::default(), steps)
+    }
+
+    /// Create a game from given size, rules and steps.
+    pub fn from_steps_with_rules(size: usize, rules: GameRules, steps: &[Point]) -> Result<Game, GameStepError> {
+        let mut game = Game::with_rules(size, rules);
 
         for point in steps {
             // Try to add each step. Return early if there is an error
-            game.add_step(point.clone())?;
+            game.add_step(*point)?;
         }
 
         Ok(game)
@@ -229,16 +400,83 @@ impl Game {
         &self.state
     }
 
+    /// Get the side that plays the next step.
+    pub fn side(&self) -> GameSide {
+        self.side
+    }
+
+    /// Get the sides in the order they take turns, starting with the side of the first step.
+    pub fn turn_order(&self) -> &[GameSide] {
+        &self.turn_order
+    }
+
+    /// Get the number of players in the game.
+    pub fn players(&self) -> usize {
+        self.turn_order.len()
+    }
+
+    /// Get the number of steps taken in the game.
+    pub fn step_count(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Get the point of the last step taken in the game, if any.
+    pub fn last_step(&self) -> Option<Point> {
+        self.steps.last().copied()
+    }
+
     /// Get the spot at a point on the game board.
     pub fn spot(&self, point: &Point) -> GameSpot {
         self.board.get_spot(point)
     }
 
+    /// Get the number of turns started in the game, including the current turn if it is
+    /// partly taken.
+    pub fn turn_count(&self) -> usize {
+        match self.steps.len() {
+            0 => 0,
+            count => self.turn_of(count - 1) + 1,
+        }
+    }
+
+    /// Get the number of stones the current side still places before the turn passes on.
+    pub fn stones_left(&self) -> usize {
+        let count = self.steps.len();
+        let first = self.rules.first_turn_stones.max(1);
+        let per_turn = self.rules.stones_per_turn.max(1);
+
+        first + self.turn_of(count) * per_turn - count
+    }
+
     /// Return an iterator that iterates over all steps of this game and their side from the start.
     pub fn iter_steps(&self) -> impl Iterator<Item=(&GameSide, &Point)> {
-        [GameSide::Black, GameSide::White].iter()
-            .cycle()
-            .zip(self.steps.iter())
+        self.steps.iter()
+            .enumerate()
+            .map(move |(i, point)| (&self.turn_order[self.turn_of(i) % self.turn_order.len()], point))
+    }
+
+    /// Return the steps of this game grouped by turn from the start, each with the side that took it.
+    /// The last turn may have fewer stones than the rules give it, if it is not over yet.
+    pub fn turns(&self) -> Vec<(GameSide, Vec<Point>)> {
+        let mut turns: Vec<(GameSide, Vec<Point>)> = vec![];
+
+        for (i, (side, point)) in self.iter_steps().enumerate() {
+            if turns.len() == self.turn_of(i) {
+                turns.push((*side, vec![]));
+            }
+            if let Some((_, points)) = turns.last_mut() {
+                points.push(*point);
+            }
+        }
+
+        turns
+    }
+
+    /// Create the game before its first step, with the same rules, players and blocked points.
+    pub fn starting_position(&self) -> Game {
+        let mut game = self.clone();
+        while game.undo_step().is_some() {}
+        game
     }
 
     /// Return an iterator that iterates over all the points on the board, row by row.
@@ -251,8 +489,12 @@ impl Game {
     pub fn validate_step(&self, point: &Point) -> Result<(), GameStepError> {
         if !self.board.is_valid(point) {
             Err(GameStepError::InvalidPoint)
+        } else if self.board.get_spot(point) == GameSpot::Blocked {
+            Err(GameStepError::Blocked)
         } else if !self.board.get_spot(point).is_empty() {
             Err(GameStepError::PointTaken)
+        } else if self.is_forbidden(point, self.side) {
+            Err(GameStepError::Forbidden)
         } else {
             Ok(())
         }
@@ -265,64 +507,201 @@ impl Game {
         self.validate_step(&point)?;
 
         self.board.set_spot(&point, GameSpot::Taken(self.side));
-        self.side = self.side.toggle();
+        self.hash ^= hash::piece_key(&point, self.side);
+        if self.ends_turn(self.steps.len()) {
+            self.side = self.next_side(self.side, 1);
+        }
         self.steps.push(point);
 
         self.update_state();
 
         Ok(())
     }
+
+    /// Add a turn to the game, placing the points in order as steps of the current side.
+    /// The points must be the stones left in the turn, or fewer if the last of them ends the game.
+    /// If the turn is invalid, do not add any step and
+    /// return a result with error of type `GameStepError`.
+    pub fn add_turn(&mut self, points: &[Point]) -> Result<(), GameStepError> {
+        let needed = self.stones_left();
+        if points.is_empty() || points.len() > needed {
+            return Err(GameStepError::InvalidTurn);
+        }
+
+        for (i, point) in points.iter().enumerate() {
+            let result = match self.state {
+                GameState::Normal => self.add_step(*point),
+                // The game ended before the last point
+                _ => Err(GameStepError::InvalidTurn),
+            };
+
+            if let Err(err) = result {
+                for _ in 0..i {
+                    self.undo_step();
+                }
+                return Err(err);
+            }
+        }
+
+        if points.len() < needed && self.state == GameState::Normal {
+            // The game goes on, so the rest of the stones of the turn are missing
+            for _ in 0..points.len() {
+                self.undo_step();
+            }
+            return Err(GameStepError::InvalidTurn);
+        }
+
+        Ok(())
+    }
+
+    /// Remove the last step from the game.
+    /// Return the point of the removed step, or `None` if there are no steps.
+    pub fn undo_step(&mut self) -> Option<Point> {
+        let point = self.steps.pop()?;
+
+        self.board.set_spot(&point, GameSpot::Empty);
+        if self.ends_turn(self.steps.len()) {
+            self.side = self.next_side(self.side, self.turn_order.len() - 1);
+        }
+        self.hash ^= hash::piece_key(&point, self.side);
+
+        self.update_state();
+
+        Some(point)
+    }
 }
 
 // Private methods
 impl Game {
+    /// Return the number of the turn, counting from 0, that the step with index belongs to.
+    fn turn_of(&self, index: usize) -> usize {
+        let first = self.rules.first_turn_stones.max(1);
+        let per_turn = self.rules.stones_per_turn.max(1);
+
+        if index < first {
+            0
+        } else {
+            1 + (index - first) / per_turn
+        }
+    }
+
+    /// Return true if the step with index is the last step of its turn, otherwise false.
+    fn ends_turn(&self, index: usize) -> bool {
+        self.turn_of(index) != self.turn_of(index + 1)
+    }
+
+    /// Return the side that takes its turn count turns after side.
+    fn next_side(&self, side: GameSide, count: usize) -> GameSide {
+        let position = self.turn_order.iter().position(|s| *s == side).unwrap_or(0);
+        self.turn_order[(position + count) % self.turn_order.len()]
+    }
+
     /// Update the state of the game. Should be called when a step is added/removed.
     fn update_state(&mut self) {
         self.state =
-        if let Some((winner_side, points)) = self.compute_winner() {
-            GameState::Finished {
-                winner_side,
-                points,
-            }
+        if let Some(state) = self.compute_winner() {
+            state
         } else if self.board.is_full() {
             GameState::BoardFull
+        } else if self.rules.early_draw && !self.is_win_possible() {
+            GameState::NoWinPossible
         } else  {
             GameState::Normal
         };
     }
 
-    /// Compute and return the winner side and points of the game, if any.
-    fn compute_winner(&self) -> Option<(GameSide, Vec<Point>)> {
+    /// Compute and return the finished state of the game, if a side made a row long enough to win,
+    /// or to lose under misère rules.
+    /// Each line holds every consecutive point of its row, and the lines are in the order
+    /// of `GameBoard::lines`, so the result does not depend on the order of the steps.
+    fn compute_winner(&self) -> Option<GameState> {
+        let mut maker = None;
+        let mut lines = vec![];
+
         for line in &self.board.lines {
-            // For each line, check whether there are repeats 5 in a row
+            // For each line, check whether there are enough repeats in a row to win
             let mut prev_spot = GameSpot::Empty;
-            let mut consec = Vec::with_capacity(5);
+            let mut consec = Vec::with_capacity(self.rules.connect);
 
-            for point in line {
-                let spot = self.board.get_spot(&point);
+            // Treat the end of the line as an empty spot, so the last row is checked too
+            for i in 0..=line.len() {
+                let spot = line.get(i).map_or(GameSpot::Empty, |point| self.board.get_spot(point));
 
-                if let GameSpot::Taken(side) = spot {
-                    if spot == prev_spot {
-                        // Consecutive side
-                        consec.push(point);
+                if spot == prev_spot && matches!(spot, GameSpot::Taken(_)) {
+                    // Consecutive side
+                    consec.push(line[i]);
+                    continue;
+                }
 
-                        if consec.len() >= 5 {
-                            let points = consec.iter().map(|pt| **pt).collect();
-                            return Some((side, points));
-                        }
-                    } else {
-                        // Different side
-                        consec.clear();
-                        consec.push(point);
+                // The previous row ended, so check whether it wins
+                if let GameSpot::Taken(side) = prev_spot {
+                    if consec.len() >= self.rules.connect && maker.is_none_or(|maker| maker == side) {
+                        maker = Some(side);
+                        lines.push(consec.clone());
                     }
-                } else {
-                    // Current spot empty
-                    consec.clear();
+                }
+
+                consec.clear();
+                if matches!(spot, GameSpot::Taken(_)) {
+                    consec.push(line[i]);
                 }
                 prev_spot = spot;
             }
         }
 
-        None
+        let maker = maker?;
+        Some(if self.rules.rule_set == RuleSet::Misere {
+            GameState::Finished {
+                winner_side: self.next_side(maker, 1),
+                loser_side: Some(maker),
+                lines,
+            }
+        } else {
+            GameState::Finished {
+                winner_side: maker,
+                loser_side: None,
+                lines,
+            }
+        })
+    }
+
+    /// Return true if any side could still complete a winning row on some line, otherwise false.
+    /// A row is still possible where connect consecutive points have no pieces of other sides
+    /// and no blocked spots.
+    fn is_win_possible(&self) -> bool {
+        let connect = self.rules.connect;
+        let sides = self.turn_order.iter().map(GameSide::index).max().unwrap_or(0) + 1;
+        // Blocked spots are counted after the sides
+        let blocked = sides;
+        let index = |point: &Point| match self.board.get_spot(point) {
+            GameSpot::Taken(side) => Some(side.index()),
+            GameSpot::Blocked => Some(blocked),
+            GameSpot::Empty => None,
+        };
+
+        self.board.lines.iter().any(|line| {
+            // Count the pieces of each side within a window sliding along the line,
+            // along with the number of sides that have pieces in it
+            let mut counts = vec![0; sides + 1];
+            let mut present = 0;
+
+            line.iter().enumerate().any(|(i, point)| {
+                if let Some(side) = index(point) {
+                    counts[side] += 1;
+                    if counts[side] == 1 {
+                        present += 1;
+                    }
+                }
+                if i >= connect {
+                    if let Some(side) = index(&line[i - connect]) {
+                        counts[side] -= 1;
+                        if counts[side] == 0 {
+                            present -= 1;
+                        }
+                    }
+                }
+                i + 1 >= connect && present <= 1 && counts[blocked] == 0
+            })
+        })
     }
 }
@@ -1,12 +1,231 @@
-use std::io;
-use connect_5_rs::{Game, GameState, GameStepError, GameSpot, GameSide, Point};
+use std::{env, fs, io, process};
+use std::path::Path;
+use connect_5_rs::{
+    BoardGeometry, Game, GameRules, GameState, GameStepError, GameSpot, GameSide, Point, Rng, RuleSet, SignedPoint,
+    UnboundedGame, UnboundedState, parse_sgf_points, random_obstacles, read_openings,
+};
+use connect_5_rs::engine::{AlphaBeta, Difficulty, Engine, SearchLimits, Weights};
+use connect_5_rs::solver::{VctResult, VctSolver, is_solvable};
+
+static USAGE: &str = "\
+Usage: connect-5-rs [options]
+
+Play a game of connect 5 in the terminal, against other players or the engine.
+
+Options:
+  --ai <difficulty>     play against the engine at a difficulty:
+                        beginner, easy, medium, hard or master
+  --ai-side <side>      the side of the engine, black or white (default white)
+  --weights <file>      pattern weights of the engine, as written by train-weights
+  --opening <opening>   start from a position, given as SGF coordinates of the steps in order
+                        such as hhig, or a random one from a file written by opening-generator
+  --players <n>         number of players taking turns, without the engine (default 2)
+  --rules <rules>       freestyle, renju, connect6, where after the first stone each turn
+                        has two stones and six in a row wins, or misere, where making
+                        five in a row loses (default freestyle)
+  --board <geometry>    square, hex, where lines run in three directions, cubic,
+                        a stack of size layers with lines in 13 directions, or unbounded,
+                        with no edges and freestyle rules for two players (default square)
+  --obstacles <n>       number of points blocked at random, which neither side may use
+                        and which break rows (default 0)
+  --size <n>            board size (default 15)";
+
+/// The largest number of players, which each have their own symbol on the board.
+static MAX_PLAYERS: usize = 9;
+
+/// The largest board size, so coordinates fit in the printed board.
+static MAX_SIZE: usize = 99;
+
+/// The smallest number of rows and columns shown of an unbounded board.
+static MIN_VIEW: i64 = 15;
+
+/// The largest number of rows and columns shown of an unbounded board, so it fits in the terminal.
+static MAX_VIEW: i64 = 25;
+
+/// The number of empty rows and columns shown around the pieces of an unbounded board.
+static VIEW_MARGIN: i64 = 2;
+
+/// Settings of the game.
+struct Options {
+    /// The difficulty and side of the engine, if playing against it.
+    ai: Option<(Difficulty, GameSide)>,
+    /// The pattern weights of the engine.
+    weights: Weights,
+    /// The steps played before the game starts.
+    opening: Vec<Point>,
+    /// The number of players taking turns.
+    players: usize,
+    /// The rules the game is played under.
+    rules: GameRules,
+    /// The shape of the cells of the board.
+    geometry: BoardGeometry,
+    /// Whether the board has no edges, instead of the geometry and size.
+    unbounded: bool,
+    /// The number of points blocked at random before the game starts.
+    obstacles: usize,
+    /// The size of the board.
+    size: usize,
+}
+
+impl Options {
+    /// Read the settings from command line arguments.
+    fn from_args(args: &[String]) -> Result<Options, String> {
+        let mut difficulty = None;
+        let mut side = GameSide::White;
+        let mut weights = Weights::default();
+        let mut opening = vec![];
+        let mut players = 2;
+        let mut rules = GameRules::default();
+        let mut geometry = BoardGeometry::Square;
+        let mut unbounded = false;
+        let mut obstacles = 0;
+        let mut size = 15;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let value = args.next().ok_or_else(|| format!("missing value for {}", arg))?;
+            let number = |text: &String| {
+                text.parse::<usize>().map_err(|_| format!("invalid number {}", text))
+            };
+
+            match &arg[..] {
+                "--ai" => difficulty = Some(Difficulty::from_name(value)
+                    .ok_or_else(|| format!("unknown difficulty {}", value))?),
+                "--ai-side" => side = match &value[..] {
+                    "black" => GameSide::Black,
+                    "white" => GameSide::White,
+                    _ => return Err(format!("unknown side {}", value)),
+                },
+                "--weights" => weights = Weights::load(value)
+                    .map_err(|err| format!("{}: {}", value, err))?,
+                "--opening" => opening = read_opening(value)?,
+                "--players" => players = number(value)?,
+                "--rules" => rules = match &value[..] {
+                    "freestyle" => GameRules::new(RuleSet::Freestyle),
+                    "renju" => GameRules::new(RuleSet::Renju),
+                    "connect6" => GameRules::connect6(),
+                    "misere" => GameRules::new(RuleSet::Misere),
+                    _ => return Err(format!("unknown rules {}", value)),
+                },
+                "--board" if value == "unbounded" => unbounded = true,
+                "--board" => geometry = BoardGeometry::from_name(value)
+                    .ok_or_else(|| format!("unknown board {}", value))?,
+                "--obstacles" => obstacles = number(value)?,
+                "--size" => size = number(value)?,
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+
+        if !(2..=MAX_PLAYERS).contains(&players) {
+            return Err(format!("number of players must be between 2 and {}", MAX_PLAYERS));
+        }
+        if players != 2 && difficulty.is_some() {
+            return Err(String::from("the engine only plays games with two players"));
+        }
+        if !rules.has_single_stone_turns() && difficulty.is_some() {
+            return Err(String::from("the engine only plays games with one stone per turn"));
+        }
+        if geometry == BoardGeometry::Cubic && difficulty.is_some() {
+            return Err(String::from("the engine only plays games on a single layer"));
+        }
+        if unbounded && (difficulty.is_some() || players != 2 || rules != GameRules::default()) {
+            return Err(String::from("an unbounded board is only played by two players under freestyle rules"));
+        }
+        if !(5..=MAX_SIZE).contains(&size) {
+            return Err(format!("board size must be between 5 and {}", MAX_SIZE));
+        }
+        if obstacles > 0 && (unbounded || obstacles > size * size / 2) {
+            return Err(String::from("obstacles must cover at most half of a bounded board"));
+        }
+
+        Ok(Options {
+            ai: difficulty.map(|difficulty| (difficulty, side)),
+            weights,
+            opening,
+            players,
+            rules,
+            geometry,
+            unbounded,
+            obstacles,
+            size,
+        })
+    }
+}
+
+/// Read an opening from a position, or choose one at random from the file at the path.
+fn read_opening(value: &str) -> Result<Vec<Point>, String> {
+    if !Path::new(value).is_file() {
+        return parse_sgf_points(value).ok_or_else(|| format!("invalid opening {}", value));
+    }
+
+    let text = fs::read_to_string(value).map_err(|err| format!("{}: {}", value, err))?;
+    let openings = read_openings(&text).map_err(|err| format!("{}: {}", value, err))?;
+    Rng::from_time().choose(&openings)
+        .cloned()
+        .ok_or_else(|| format!("{}: no openings", value))
+}
 
 fn main() {
-    let mut game = Game::new(15);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match Options::from_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(1);
+        },
+    };
+
+    if options.unbounded {
+        play_unbounded(&options);
+        return;
+    }
+
+    let mut engine = AlphaBeta::with_weights(options.weights.clone());
+    if let Some((difficulty, _)) = options.ai {
+        engine.set_difficulty(difficulty);
+        engine.set_seed(Rng::from_time().next_u64());
+    }
+
+    // End the game as soon as neither side can win, rather than playing on until the board is full
+    let rules = GameRules {
+        early_draw: true,
+        geometry: options.geometry,
+        ..options.rules
+    };
+    let mut game = Game::with_players(options.size, rules, options.players)
+        .expect("number of players should be validated");
+    for point in random_obstacles(&game, options.obstacles, &mut Rng::from_time()) {
+        game.block(point).expect("random obstacles should be empty points");
+    }
+    for point in &options.opening {
+        if let Err(err) = game.add_step(*point) {
+            eprintln!("Invalid opening: {}", err);
+            process::exit(1);
+        }
+    }
     print_board(&game);
 
     while let GameState::Normal = game.state() {
-        let point = get_point(&game);
+        let point = match options.ai {
+            Some((_, side)) if side == game.side() => match engine.search(&game, &SearchLimits::default()) {
+                Some(result) => {
+                    println!("The engine plays {} {}", result.best_step.x, result.best_step.y);
+                    result.best_step
+                },
+                None => {
+                    println!("The engine has no step it may play.");
+                    break;
+                },
+            },
+            _ => {
+                if game.stones_left() > 1 {
+                    println!("{} to play, {} stones left in the turn", side_name(game.side()), game.stones_left());
+                } else if game.players() > 2 || !game.rules().has_single_stone_turns() {
+                    println!("{} to play", side_name(game.side()));
+                }
+                get_point(&game)
+            },
+        };
         game.add_step(point).expect("This should never happen.");
         print_board(&game);
     }
@@ -15,38 +234,75 @@ fn main() {
         GameState::BoardFull => {
             println!("Tie: board is full.");
         },
-        GameState::Finished { winner_side, points: _ } => {
-            println!("Winner: {}", match winner_side {
-                GameSide::Black => "Black",
-                GameSide::White => "White",
-            });
+        GameState::NoWinPossible => {
+            println!("Tie: neither side can win.");
+        },
+        GameState::Finished { winner_side, loser_side: Some(loser_side), lines: _ } => {
+            println!("Loser: {}, who made a row. Winner: {}", side_name(*loser_side), side_name(*winner_side));
+        },
+        GameState::Finished { winner_side, loser_side: None, lines: _ } => {
+            println!("Winner: {}", side_name(*winner_side));
         },
         GameState::Normal => (),
     }
 }
 
+/// Get the name of a side, as shown to the players.
+fn side_name(side: GameSide) -> String {
+    match side {
+        GameSide::Black => String::from("Black"),
+        GameSide::White => String::from("White"),
+        side => format!("Player {} ({})", side.index() + 1, side_symbol(side)),
+    }
+}
+
+/// Get the symbol of a side on the printed board. Further players are shown by their number.
+fn side_symbol(side: GameSide) -> char {
+    match side {
+        GameSide::Black => 'B',
+        GameSide::White => 'W',
+        side => std::char::from_digit(side.index() as u32 + 1, 10).unwrap_or('?'),
+    }
+}
+
 /// Get a point from user input to place on the board.
 /// It is guaranteed that the point is valid for step.
 fn get_point(game: &Game) -> Point {
+    let cubic = game.geometry() == BoardGeometry::Cubic;
+    // The threat solver gives no result for the rules it does not support
+    let solvable = is_solvable(game);
+
     loop {
-        println!("Enter coordinate, in format: x y");
+        if cubic {
+            println!("Enter coordinate, in format: x y z, where z is the layer");
+        } else if solvable {
+            println!("Enter coordinate, in format: x y, or vct to search for a forced win");
+        } else {
+            println!("Enter coordinate, in format: x y");
+        }
 
         let mut input = String::new();
 
         io::stdin().read_line(&mut input)
             .expect("Failed to read line");
 
+        if input.trim() == "vct" && solvable {
+            print_threats(game);
+            continue;
+        }
+
         let nums: Vec<Option<usize>> = input
             .split_whitespace()
             .map(|n_str| n_str.parse().ok())
             .collect();
 
-        // Match exactly two parsed number elements to create point
-        let point = match &nums[..] {
-            &[Some(x), Some(y)] => Point::new(x, y),
+        // Match exactly two parsed number elements to create point, or three on a cubic board
+        let point = match nums[..] {
+            [Some(x), Some(y)] if !cubic => Point::new(x, y),
+            [Some(x), Some(y), Some(z)] if cubic => Point::with_z(x, y, z),
             _ => {
                 // Otherwise, invalid input
-                println!("Please enter two valid numbers separated by whitespace.");
+                println!("Please enter {} valid numbers separated by whitespace.", if cubic { "three" } else { "two" });
                 continue;
             },
         };
@@ -62,32 +318,174 @@ fn get_point(game: &Game) -> Point {
                 println!("Please choose a point that is on the board.");
                 continue;
             },
+            Err(GameStepError::Blocked) => {
+                println!("The point is blocked, try again.");
+                continue;
+            },
+            Err(GameStepError::Forbidden) => {
+                println!("The point is forbidden by the rules, try again.");
+                continue;
+            },
+            Err(GameStepError::InvalidTurn) => {
+                // Never returned for a single step
+                continue;
+            },
         };
     }
 }
 
 /// Print the formatted board.
 fn print_board(game: &Game) {
-    let x_coords: String = (0..game.size())
-        .map(|x| format!("{:2} ", x))
+    match game.geometry() {
+        BoardGeometry::Square => print_layer(game, 0, 3, 0),
+        // Each row of a hex board is shifted half a cell to the right of the row above
+        BoardGeometry::Hex => print_layer(game, 0, 4, 2),
+        BoardGeometry::Cubic => {
+            for z in 0..game.size() {
+                println!("Layer {}", z);
+                print_layer(game, z, 3, 0);
+            }
+        },
+    }
+}
+
+/// Print the layer z of the board, with cells of width characters,
+/// where each row is shifted by shift characters more than the row above.
+fn print_layer(game: &Game, z: usize, width: usize, shift: usize) {
+    let size = game.size();
+
+    let x_coords: String = (0..size)
+        .map(|x| format!("{:>1$} ", x, width - 1))
         .collect();
     println!("     {}", x_coords);
 
-    let bars: String = (0..game.size())
-        .map(|_| "---")
-        .collect();
+    let bars = "-".repeat(width * size + shift * (size - 1));
     println!("     {}", bars);
 
-    for y in 0..game.size() {
-        print!("{:2} | ", y);
-        for x in 0..game.size() {
-            let chr = match game.spot(&Point::new(x, y)) {
+    for y in 0..size {
+        print!("{:2} | {}", y, " ".repeat(shift * y));
+        for x in 0..size {
+            let chr = match game.spot(&Point::with_z(x, y, z)) {
                 GameSpot::Empty => '.',
-                GameSpot::Taken(GameSide::Black) => 'B',
-                GameSpot::Taken(GameSide::White) => 'W',
+                GameSpot::Taken(side) => side_symbol(side),
+                GameSpot::Blocked => '#',
             };
-            print!(" {} ", chr);
+            print!("{:>1$} ", chr, width - 1);
         }
         println!();
     }
 }
+
+/// Play a game on an unbounded board, where the printed part of the board follows the pieces.
+fn play_unbounded(options: &Options) {
+    let mut game = UnboundedGame::new();
+    for point in &options.opening {
+        if let Err(err) = game.add_step(SignedPoint::new(point.x as i64, point.y as i64)) {
+            eprintln!("Invalid opening: {}", err);
+            process::exit(1);
+        }
+    }
+    print_unbounded(&game);
+
+    while let UnboundedState::Normal = game.state() {
+        let point = get_signed_point(&game);
+        game.add_step(point).expect("This should never happen.");
+        print_unbounded(&game);
+    }
+
+    if let UnboundedState::Finished { winner_side, lines: _ } = game.state() {
+        println!("Winner: {}", side_name(*winner_side));
+    }
+}
+
+/// Get a point from user input to place on an unbounded board.
+/// It is guaranteed that the point is valid for step.
+fn get_signed_point(game: &UnboundedGame) -> SignedPoint {
+    loop {
+        println!("{} to play. Enter coordinate, in format: x y, which may be negative", side_name(game.side()));
+
+        let mut input = String::new();
+
+        io::stdin().read_line(&mut input)
+            .expect("Failed to read line");
+
+        let nums: Vec<Option<i64>> = input
+            .split_whitespace()
+            .map(|n_str| n_str.parse().ok())
+            .collect();
+
+        let point = match nums[..] {
+            [Some(x), Some(y)] => SignedPoint::new(x, y),
+            _ => {
+                println!("Please enter two valid numbers separated by whitespace.");
+                continue;
+            },
+        };
+
+        match game.validate_step(&point) {
+            Ok(_) => return point,
+            Err(_) => {
+                println!("The point is already taken by another piece, try again.");
+                continue;
+            },
+        };
+    }
+}
+
+/// Print the part of an unbounded board around the pieces.
+fn print_unbounded(game: &UnboundedGame) {
+    let (min, max) = game.bounds().unwrap_or((SignedPoint::new(0, 0), SignedPoint::new(0, 0)));
+    let last = game.last_step().unwrap_or(min);
+    let (start_x, width) = view_range(min.x, max.x, last.x);
+    let (start_y, height) = view_range(min.y, max.y, last.y);
+
+    let x_coords: String = (start_x..start_x.saturating_add(width))
+        .map(|x| format!("{:>3} ", x))
+        .collect();
+    println!("       {}", x_coords);
+    println!("       {}", "-".repeat(4 * width as usize));
+
+    for y in start_y..start_y.saturating_add(height) {
+        print!("{:>4} | ", y);
+        for x in start_x..start_x.saturating_add(width) {
+            let chr = match game.spot(&SignedPoint::new(x, y)) {
+                GameSpot::Empty => '.',
+                GameSpot::Taken(side) => side_symbol(side),
+                // An unbounded board has no blocked spots
+                GameSpot::Blocked => '#',
+            };
+            print!("{:>3} ", chr);
+        }
+        println!();
+    }
+}
+
+/// Return the first coordinate and the number of coordinates to show along one axis of an
+/// unbounded board, where the pieces lie from low to high. The pieces are shown with a margin,
+/// and if they do not fit, the view is centered on focus, the coordinate of the last step.
+fn view_range(low: i64, high: i64, focus: i64) -> (i64, i64) {
+    let (low, high) = (low.saturating_sub(VIEW_MARGIN), high.saturating_add(VIEW_MARGIN));
+    let count = high.saturating_sub(low).saturating_add(1);
+
+    if count < MIN_VIEW {
+        (low.saturating_sub((MIN_VIEW - count) / 2), MIN_VIEW)
+    } else if count > MAX_VIEW {
+        (focus.saturating_sub(MAX_VIEW / 2), MAX_VIEW)
+    } else {
+        (low, count)
+    }
+}
+
+/// Search for a win by continuous threats for the side to move, and print the result.
+fn print_threats(game: &Game) {
+    let mut solver = VctSolver::default();
+
+    match solver.solve(game, game.side()) {
+        VctResult::Win(tree) => {
+            println!("Forced win found, your steps marked with + and replies with -:");
+            print!("{}", tree);
+        },
+        VctResult::NoWin => println!("No forced win found."),
+        VctResult::Unknown => println!("Search stopped after {} positions without a result.", solver.nodes()),
+    }
+}
@@ -0,0 +1,44 @@
+use crate::{Game, GameSpot, GameStepError, Point, Rng};
+
+// Obstacles
+impl Game {
+    /// Block an empty point on the board, so no side may use it and it breaks any row through it.
+    /// Blocked points stay blocked when steps are undone.
+    /// If the point is not empty or outside the board, return a result with error of type `GameStepError`.
+    pub fn block(&mut self, point: Point) -> Result<(), GameStepError> {
+        if !self.board.is_valid(&point) {
+            return Err(GameStepError::InvalidPoint);
+        }
+        match self.board.get_spot(&point) {
+            GameSpot::Empty => (),
+            GameSpot::Blocked => return Err(GameStepError::Blocked),
+            GameSpot::Taken(_) => return Err(GameStepError::PointTaken),
+        }
+
+        self.board.set_spot(&point, GameSpot::Blocked);
+        self.update_state();
+
+        Ok(())
+    }
+
+    /// Get the blocked points on the board, row by row.
+    pub fn obstacles(&self) -> Vec<Point> {
+        self.iter_points()
+            .filter(|p| self.spot(p) == GameSpot::Blocked)
+            .copied()
+            .collect()
+    }
+}
+
+/// Return count different empty points of game, chosen at random, to block as obstacles.
+/// If there are fewer empty points, return all of them in a random order.
+pub fn random_obstacles(game: &Game, count: usize, rng: &mut Rng) -> Vec<Point> {
+    let mut points: Vec<Point> = game.iter_points()
+        .filter(|p| game.spot(p).is_empty())
+        .copied()
+        .collect();
+
+    rng.shuffle(&mut points);
+    points.truncate(count);
+    points
+}
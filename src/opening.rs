@@ -0,0 +1,158 @@
+use crate::{Game, GameRecord, GameSide, GameState, Point, RecordError, Rng, parse_sgf_points};
+use crate::engine::{AlphaBeta, Engine, SearchLimits, Weights};
+
+/// The distance from the center within which stones are placed when no other value is given.
+static DEFAULT_RADIUS: usize = 3;
+
+/// The largest score of a balanced opening when no other value is given.
+static DEFAULT_BALANCE: i32 = 300;
+
+/// The depth searched to evaluate an opening when no other value is given.
+static DEFAULT_DEPTH: usize = 2;
+
+/// The number of random positions tried for each opening when no other value is given.
+static DEFAULT_MAX_TRIES: usize = 1000;
+
+/// A generator of random openings where neither side is winning, for testing engines
+/// from many different starting positions.
+///
+/// Stones are placed at random near the center of the board, alternating sides as in a game,
+/// and the position is kept if neither side can make five on the next step and the engine
+/// scores it close to even.
+#[derive(Clone, Debug)]
+pub struct OpeningGenerator {
+    size: usize,
+    stones: usize,
+    radius: usize,
+    balance: i32,
+    depth: usize,
+    max_tries: usize,
+    weights: Weights,
+}
+
+// Initializers
+impl OpeningGenerator {
+    /// Create a new generator of openings with stones on a board with size.
+    pub fn new(size: usize, stones: usize) -> OpeningGenerator {
+        OpeningGenerator {
+            size,
+            stones,
+            radius: DEFAULT_RADIUS,
+            balance: DEFAULT_BALANCE,
+            depth: DEFAULT_DEPTH,
+            max_tries: DEFAULT_MAX_TRIES,
+            weights: Weights::default(),
+        }
+    }
+}
+
+// Public methods
+impl OpeningGenerator {
+    /// Set the largest distance from the center, in either direction, of the stones.
+    pub fn set_radius(&mut self, radius: usize) {
+        self.radius = radius;
+    }
+
+    /// Set the largest score, from the view of the side to move, of a balanced opening.
+    pub fn set_balance(&mut self, balance: i32) {
+        self.balance = balance.abs();
+    }
+
+    /// Set the depth searched by the engine to score an opening.
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth.max(1);
+    }
+
+    /// Set the number of random positions tried before giving up on an opening.
+    pub fn set_max_tries(&mut self, max_tries: usize) {
+        self.max_tries = max_tries.max(1);
+    }
+
+    /// Set the weights of the engine that scores an opening.
+    pub fn set_weights(&mut self, weights: Weights) {
+        self.weights = weights;
+    }
+
+    /// Return a random position that meets the constraints on where stones are placed,
+    /// without checking whether it is balanced, or `None` if none was found within the tries.
+    pub fn random_position(&self, rng: &mut Rng) -> Option<Game> {
+        (0..self.max_tries).find_map(|_| self.try_position(rng))
+    }
+
+    /// Return a random position that meets the constraints and is balanced,
+    /// or `None` if none was found within the tries.
+    pub fn generate(&self, rng: &mut Rng) -> Option<Game> {
+        let mut engine = AlphaBeta::with_weights(self.weights.clone());
+        let limits = SearchLimits::depth(self.depth);
+
+        (0..self.max_tries).find_map(|_| {
+            let game = self.try_position(rng)?;
+            let result = engine.search(&game, &limits)?;
+            if result.score.abs() <= self.balance {
+                Some(game)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+// Private methods
+impl OpeningGenerator {
+    /// Place the stones at random once, and return the position if it meets the constraints.
+    fn try_position(&self, rng: &mut Rng) -> Option<Game> {
+        let center = self.size / 2;
+        let low = center.saturating_sub(self.radius);
+        let high = (center + self.radius).min(self.size.saturating_sub(1));
+        let width = high - low + 1;
+
+        let mut game = Game::new(self.size);
+        let mut misses = 0;
+        while game.step_count() < self.stones {
+            let point = Point::new(low + rng.below(width), low + rng.below(width));
+            if game.add_step(point).is_err() {
+                // The area may be too small for the stones
+                misses += 1;
+                if misses > width * width * 4 {
+                    return None;
+                }
+                continue;
+            }
+            if !matches!(game.state(), GameState::Normal) {
+                return None;
+            }
+        }
+
+        let quiet = game.iter_points()
+            .filter(|p| game.spot(p).is_empty())
+            .all(|p| !game.is_winning_step(p, GameSide::Black) && !game.is_winning_step(p, GameSide::White));
+
+        if quiet {
+            Some(game)
+        } else {
+            None
+        }
+    }
+}
+
+/// Read openings from text, which is either an SGF collection whose main lines are the openings,
+/// or one position on each line as SGF coordinates of the steps in order, such as `hhighi`.
+/// Empty lines and lines starting with `#` are ignored.
+pub fn read_openings(text: &str) -> Result<Vec<Vec<Point>>, RecordError> {
+    if text.trim_start().starts_with('(') {
+        return GameRecord::from_sgf_collection(text)
+            .map(|records| records.iter().map(GameRecord::main_line).collect());
+    }
+
+    let mut openings = vec![];
+    let mut offset = 0;
+    for line in text.lines() {
+        let position = line.trim();
+        if !position.is_empty() && !position.starts_with('#') {
+            openings.push(parse_sgf_points(position).ok_or(RecordError::Syntax(offset))?);
+        }
+        offset += line.len() + 1;
+    }
+
+    Ok(openings)
+}
@@ -0,0 +1,209 @@
+//! The Gomocup text protocol, used by Piskvork to talk to engines ("brains") over
+//! standard input and output.
+//!
+//! See <https://plastovicka.github.io/protocl2en.htm> for the full specification.
+
+use std::fmt;
+
+use crate::Point;
+
+/// The owner of a stone sent with the `BOARD` command, from the view of the brain.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum BoardField {
+    /// A stone of the brain.
+    Own,
+    /// A stone of the opponent.
+    Opponent,
+    /// A stone of a continuous game, used by some rule sets.
+    Continuous,
+}
+
+/// A command sent from a manager to a brain.
+#[derive(PartialEq, Clone, Debug)]
+pub enum Command {
+    /// Start a new game on a square board with size.
+    Start(usize),
+    /// Start a new game on a rectangular board with width and height.
+    RectStart(usize, usize),
+    /// Start a new game on the same board.
+    Restart,
+    /// Ask the brain to play the first step.
+    Begin,
+    /// Tell the brain about the step of the opponent, and ask for a reply.
+    Turn(Point),
+    /// Set up a position, in the order the stones were played, and ask for a step.
+    Board(Vec<(Point, BoardField)>),
+    /// Tell the brain about a setting, such as `timeout_turn`, as a key and value.
+    Info(String, String),
+    /// Remove a stone from the board.
+    Takeback(Point),
+    /// Ask the brain for information about itself.
+    About,
+    /// Ask the brain to exit.
+    End,
+    /// A command that is not understood.
+    Unknown(String),
+}
+
+impl Command {
+    /// Parse a command from a line of text. For `BOARD`, only the first line is parsed,
+    /// and the stones must be added with `parse_board_line` until it returns `None`.
+    pub fn parse(line: &str) -> Command {
+        let line = line.trim();
+        let (name, args) = match line.find(char::is_whitespace) {
+            Some(i) => (&line[..i], line[i..].trim()),
+            None => (line, ""),
+        };
+
+        let command = match &name.to_ascii_uppercase()[..] {
+            "START" => args.parse().ok().map(Command::Start),
+            "RECTSTART" => parse_pair(args).map(|(w, h)| Command::RectStart(w, h)),
+            "RESTART" => Some(Command::Restart),
+            "BEGIN" => Some(Command::Begin),
+            "TURN" => parse_point(args).map(Command::Turn),
+            "BOARD" => Some(Command::Board(vec![])),
+            "INFO" => {
+                let mut parts = args.splitn(2, char::is_whitespace);
+                match (parts.next(), parts.next()) {
+                    (Some(key), Some(value)) if !key.is_empty() => {
+                        Some(Command::Info(key.to_ascii_lowercase(), String::from(value.trim())))
+                    },
+                    _ => None,
+                }
+            },
+            "TAKEBACK" => parse_point(args).map(Command::Takeback),
+            "ABOUT" => Some(Command::About),
+            "END" => Some(Command::End),
+            _ => None,
+        };
+
+        command.unwrap_or_else(|| Command::Unknown(String::from(line)))
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::Start(size) => write!(f, "START {}", size),
+            Command::RectStart(width, height) => write!(f, "RECTSTART {},{}", width, height),
+            Command::Restart => write!(f, "RESTART"),
+            Command::Begin => write!(f, "BEGIN"),
+            Command::Turn(point) => write!(f, "TURN {}", format_point(point)),
+            Command::Board(stones) => {
+                writeln!(f, "BOARD")?;
+                for (point, field) in stones {
+                    let field = match field {
+                        BoardField::Own => 1,
+                        BoardField::Opponent => 2,
+                        BoardField::Continuous => 3,
+                    };
+                    writeln!(f, "{},{}", format_point(point), field)?;
+                }
+                write!(f, "DONE")
+            },
+            Command::Info(key, value) => write!(f, "INFO {} {}", key, value),
+            Command::Takeback(point) => write!(f, "TAKEBACK {}", format_point(point)),
+            Command::About => write!(f, "ABOUT"),
+            Command::End => write!(f, "END"),
+            Command::Unknown(line) => write!(f, "{}", line),
+        }
+    }
+}
+
+/// A reply sent from a brain to a manager.
+#[derive(PartialEq, Clone, Debug)]
+pub enum Response {
+    /// The brain accepted the command.
+    Ok,
+    /// The step played by the brain.
+    Step(Point),
+    /// A step suggested by the brain for a swap or opening rule.
+    Suggest(Point),
+    /// A message to show to the user.
+    Message(String),
+    /// A debugging message.
+    Debug(String),
+    /// The brain could not handle the command, with a description.
+    Error(String),
+    /// The brain did not understand the command.
+    Unknown(String),
+    /// Any other line, such as the reply to `ABOUT`.
+    Other(String),
+}
+
+impl Response {
+    /// Parse a reply from a line of text.
+    pub fn parse(line: &str) -> Response {
+        let line = line.trim();
+        let (name, args) = match line.find(char::is_whitespace) {
+            Some(i) => (&line[..i], line[i..].trim()),
+            None => (line, ""),
+        };
+
+        match &name.to_ascii_uppercase()[..] {
+            "OK" if args.is_empty() => Response::Ok,
+            "MESSAGE" => Response::Message(String::from(args)),
+            "DEBUG" => Response::Debug(String::from(args)),
+            "ERROR" => Response::Error(String::from(args)),
+            "UNKNOWN" => Response::Unknown(String::from(args)),
+            "SUGGEST" => match parse_point(args) {
+                Some(point) => Response::Suggest(point),
+                None => Response::Other(String::from(line)),
+            },
+            _ => match parse_point(line) {
+                Some(point) => Response::Step(point),
+                None => Response::Other(String::from(line)),
+            },
+        }
+    }
+}
+
+/// Get the value of a key from the reply to `ABOUT`, in the form `key="value", key="value"`.
+pub fn about_value(about: &str, key: &str) -> Option<String> {
+    let pattern = format!("{}=\"", key);
+    let start = about.find(&pattern)? + pattern.len();
+    let end = about[start..].find('"')? + start;
+
+    Some(String::from(&about[start..end]))
+}
+
+/// Parse a line following `BOARD`, in the form `x,y,field`.
+/// Return `None` when the line is `DONE` or cannot be parsed.
+pub fn parse_board_line(line: &str) -> Option<(Point, BoardField)> {
+    let parts: Vec<Option<usize>> = line.trim()
+        .split(',')
+        .map(|n| n.trim().parse().ok())
+        .collect();
+
+    match &parts[..] {
+        &[Some(x), Some(y), Some(field)] => {
+            let field = match field {
+                1 => BoardField::Own,
+                2 => BoardField::Opponent,
+                3 => BoardField::Continuous,
+                _ => return None,
+            };
+            Some((Point::new(x, y), field))
+        },
+        _ => None,
+    }
+}
+
+/// Format a point in the form `x,y`.
+pub fn format_point(point: &Point) -> String {
+    format!("{},{}", point.x, point.y)
+}
+
+/// Parse a point in the form `x,y`.
+pub fn parse_point(text: &str) -> Option<Point> {
+    parse_pair(text).map(|(x, y)| Point::new(x, y))
+}
+
+/// Parse two numbers separated by a comma.
+fn parse_pair(text: &str) -> Option<(usize, usize)> {
+    let mut parts = text.trim().split(',');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(a), Some(b), None) => Some((a.trim().parse().ok()?, b.trim().parse().ok()?)),
+        _ => None,
+    }
+}
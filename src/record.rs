@@ -0,0 +1,781 @@
+use std::error;
+use std::fmt;
+
+use crate::{BoardGeometry, Game, GameRules, GameSide, GameState, GameStepError, Point, RuleSet};
+
+/// The SGF game type of gomoku and its variants.
+static SGF_GAME_TYPE: &str = "4";
+
+/// The largest board size that can be written with SGF coordinates.
+pub static MAX_SGF_SIZE: usize = 52;
+
+/// Metadata describing a recorded game.
+#[derive(PartialEq, Clone, Default, Debug)]
+pub struct RecordMetadata {
+    /// Name of the black player, stored as `PB`.
+    pub black: Option<String>,
+    /// Name of the white player, stored as `PW`.
+    pub white: Option<String>,
+    /// Date the game was played, stored as `DT`.
+    pub date: Option<String>,
+    /// Name of the event the game was played in, stored as `EV`.
+    pub event: Option<String>,
+    /// Rule set the game was played under, stored as `RU`.
+    pub rules: Option<String>,
+    /// Result of the game, stored as `RE`, e.g. `"B+"`, `"W+"` or `"Draw"`.
+    pub result: Option<String>,
+}
+
+/// An annotation on a single move, as used in teaching games.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum MoveAnnotation {
+    /// A good move, stored as `TE`.
+    Good,
+    /// A bad move, stored as `BM`.
+    Bad,
+    /// An interesting move, stored as `IT`.
+    Interesting,
+    /// A doubtful move, stored as `DO`.
+    Doubtful,
+}
+
+impl MoveAnnotation {
+    /// Return the SGF property identifier of the annotation.
+    fn property(&self) -> &'static str {
+        match self {
+            MoveAnnotation::Good => "TE",
+            MoveAnnotation::Bad => "BM",
+            MoveAnnotation::Interesting => "IT",
+            MoveAnnotation::Doubtful => "DO",
+        }
+    }
+
+    /// Return the SGF property value of the annotation.
+    fn value(&self) -> &'static str {
+        match self {
+            MoveAnnotation::Good | MoveAnnotation::Bad => "1",
+            MoveAnnotation::Interesting | MoveAnnotation::Doubtful => "",
+        }
+    }
+
+    /// Get the annotation from an SGF property identifier, if it is one.
+    fn from_property(ident: &str) -> Option<MoveAnnotation> {
+        match ident {
+            "TE" => Some(MoveAnnotation::Good),
+            "BM" => Some(MoveAnnotation::Bad),
+            "IT" => Some(MoveAnnotation::Interesting),
+            "DO" => Some(MoveAnnotation::Doubtful),
+            _ => None,
+        }
+    }
+}
+
+/// A single move in a game record, along with the moves that may follow it.
+#[derive(PartialEq, Clone, Debug)]
+pub struct RecordNode {
+    /// The point of the move.
+    pub point: Point,
+    /// A comment on the position after the move.
+    pub comment: Option<String>,
+    /// Annotations on the move.
+    pub annotations: Vec<MoveAnnotation>,
+    /// The moves that may follow this move. The first one is the main line,
+    /// and the others are variations.
+    pub children: Vec<RecordNode>,
+}
+
+impl RecordNode {
+    /// Create a new node at point with no comment, annotations or children.
+    pub fn new(point: Point) -> RecordNode {
+        RecordNode {
+            point,
+            comment: None,
+            annotations: vec![],
+            children: vec![],
+        }
+    }
+}
+
+/// An error that is caused by reading an invalid game record.
+#[derive(Debug)]
+pub enum RecordError {
+    /// The text is not well-formed, with the byte offset where the problem was found.
+    Syntax(usize),
+
+    /// A property has a value that cannot be understood.
+    InvalidProperty(String),
+
+    /// The record describes a game type other than gomoku.
+    UnsupportedGame,
+
+    /// A move in the record is played by the wrong side.
+    WrongSide(Point),
+
+    /// A move in the record cannot be added to the game.
+    InvalidStep(Point, GameStepError),
+
+    /// The board is larger than `MAX_SGF_SIZE`, so its points cannot be written.
+    BoardTooLarge(usize),
+
+    /// The game has players other than black and white, or blocked points, which a record cannot hold.
+    UnsupportedPosition,
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordError::Syntax(offset) => write!(f, "syntax error at offset {}", offset),
+            RecordError::InvalidProperty(ident) => write!(f, "invalid value for property {}", ident),
+            RecordError::UnsupportedGame => write!(f, "record is not a gomoku game"),
+            RecordError::WrongSide(p) => write!(f, "move ({}, {}) is played by the wrong side", p.x, p.y),
+            RecordError::InvalidStep(p, err) => write!(f, "invalid move ({}, {}): {}", p.x, p.y, err),
+            RecordError::BoardTooLarge(size) => write!(f, "board size {} is too large for SGF", size),
+            RecordError::UnsupportedPosition => write!(f, "record can only hold games of black and white without blocked points"),
+        }
+    }
+}
+
+impl error::Error for RecordError {}
+
+/// A record of a game, with metadata and a tree of annotated moves.
+///
+/// Records are stored in an SGF-like text format, for example:
+///
+/// ```text
+/// (;GM[4]FF[4]SZ[15]PB[Alice]PW[Bob]RE[B+];B[hh]C[Center opening];W[ig](;B[gi]TE[1])(;B[ii]))
+/// ```
+///
+/// Points are written as two letters, `a` to `z` followed by `A` to `Z`,
+/// for the x and y coordinates respectively. On a cubic board, a third letter is added
+/// for the layer of points that are not on the first layer.
+///
+/// Rules other than the defaults are stored in the first node, as the geometry `GE`,
+/// the rule set `RS`, the winning length `CN`, early draws `ED`, and the number of stones
+/// in the first turn `FS` and in every other turn `NS`. For example, Connect6 is stored as
+/// `CN[6]NS[2]`, and the stones of a turn are written in the same node, e.g. `;W[bb][cc]`.
+#[derive(PartialEq, Clone, Debug)]
+pub struct GameRecord {
+    /// The size of the board.
+    size: usize,
+    /// The rules the game is played under.
+    rules: GameRules,
+    /// The metadata of the game.
+    pub metadata: RecordMetadata,
+    /// A comment on the starting position.
+    pub comment: Option<String>,
+    /// The first moves of the game. The first one is the main line, and the others are variations.
+    pub variations: Vec<RecordNode>,
+}
+
+// Initializers
+impl GameRecord {
+    /// Create an empty record for a board with size.
+    pub fn new(size: usize) -> GameRecord {
+        GameRecord::with_rules(size, GameRules::default())
+    }
+
+    /// Create an empty record for a board with size, played under rules.
+    pub fn with_rules(size: usize, rules: GameRules) -> GameRecord {
+        GameRecord {
+            size,
+            rules,
+            metadata: RecordMetadata::default(),
+            comment: None,
+            variations: vec![],
+        }
+    }
+
+    /// Create a record with the steps of a game as its main line.
+    /// The result is filled in from the state of the game.
+    ///
+    /// If the board is larger than `MAX_SGF_SIZE`, or the game has players other than black
+    /// and white or blocked points, return a result with error of type `RecordError`.
+    pub fn from_game(game: &Game) -> Result<GameRecord, RecordError> {
+        if game.size() > MAX_SGF_SIZE {
+            return Err(RecordError::BoardTooLarge(game.size()));
+        }
+        // SGF only has black and white moves, and no way to block points
+        if game.turn_order() != [GameSide::Black, GameSide::White] || !game.obstacles().is_empty() {
+            return Err(RecordError::UnsupportedPosition);
+        }
+        let mut record = GameRecord::with_rules(game.size(), *game.rules());
+
+        record.metadata.result = match game.state() {
+            GameState::Normal => None,
+            GameState::BoardFull | GameState::NoWinPossible => Some(String::from("Draw")),
+            GameState::Finished { winner_side: GameSide::Black, .. } => Some(String::from("B+")),
+            GameState::Finished { winner_side: GameSide::White, .. } => Some(String::from("W+")),
+            // Games with further players are not recorded
+            GameState::Finished { winner_side: GameSide::Player(_), .. } => None,
+        };
+
+        let steps: Vec<Point> = game.iter_steps().map(|(_, p)| *p).collect();
+        record.add_line(&[], &steps);
+
+        Ok(record)
+    }
+
+    /// Read a record from text in SGF format.
+    /// If the text contains more than one game, only the first is read.
+    pub fn from_sgf(text: &str) -> Result<GameRecord, RecordError> {
+        let mut parser = SgfParser::new(text);
+        let tree = parser.parse_tree()?;
+
+        GameRecord::from_sgf_tree(tree)
+    }
+
+    /// Read every record from text in SGF format that contains a collection of games.
+    pub fn from_sgf_collection(text: &str) -> Result<Vec<GameRecord>, RecordError> {
+        let mut parser = SgfParser::new(text);
+        let mut records = vec![];
+
+        while parser.skip_whitespace() {
+            records.push(GameRecord::from_sgf_tree(parser.parse_tree()?)?);
+        }
+
+        Ok(records)
+    }
+}
+
+// Public methods
+impl GameRecord {
+    /// Get the size of the board.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Get the rules the game is played under.
+    pub fn rules(&self) -> &GameRules {
+        &self.rules
+    }
+
+    /// Return the points of the main line, following the first move at every node.
+    pub fn main_line(&self) -> Vec<Point> {
+        let mut points = vec![];
+        let mut nodes = &self.variations;
+
+        while let Some(node) = nodes.first() {
+            points.push(node.point);
+            nodes = &node.children;
+        }
+
+        points
+    }
+
+    /// Get the node at path, where each element of the path is the index of the
+    /// chosen move among the possible moves at that depth.
+    pub fn node(&self, path: &[usize]) -> Option<&RecordNode> {
+        let (last, rest) = path.split_last()?;
+        let mut nodes = &self.variations;
+
+        for &i in rest {
+            nodes = &nodes.get(i)?.children;
+        }
+
+        nodes.get(*last)
+    }
+
+    /// Get the node at path mutably. See `node` for the format of the path.
+    pub fn node_mut(&mut self, path: &[usize]) -> Option<&mut RecordNode> {
+        let (last, rest) = path.split_last()?;
+        let mut nodes = &mut self.variations;
+
+        for &i in rest {
+            nodes = &mut nodes.get_mut(i)?.children;
+        }
+
+        nodes.get_mut(*last)
+    }
+
+    /// Create the game at the node at path, or the starting position if the path is empty.
+    /// Return `None` if there is no node at path.
+    pub fn game_at(&self, path: &[usize]) -> Option<Game> {
+        let mut game = Game::with_rules(self.size, self.rules);
+        let mut nodes = &self.variations;
+
+        for &i in path {
+            let node = nodes.get(i)?;
+            // Records are validated when read, and points are only added through `add_line`
+            game.add_step(node.point).ok()?;
+            nodes = &node.children;
+        }
+
+        Some(game)
+    }
+
+    /// Add a line of moves after the node at path, reusing existing nodes where the moves match.
+    /// New moves become variations unless there are no moves yet at that point.
+    ///
+    /// Return the path to the last node of the line,
+    /// or `None` if there is no node at path or any of the moves is invalid.
+    pub fn add_line(&mut self, path: &[usize], points: &[Point]) -> Option<Vec<usize>> {
+        // Validate all the moves before changing the tree
+        let mut game = self.game_at(path)?;
+        for point in points {
+            game.add_step(*point).ok()?;
+        }
+
+        let mut path = path.to_vec();
+        for point in points {
+            let nodes = if path.is_empty() {
+                &mut self.variations
+            } else {
+                &mut self.node_mut(&path)?.children
+            };
+
+            let index = match nodes.iter().position(|node| node.point == *point) {
+                Some(index) => index,
+                None => {
+                    nodes.push(RecordNode::new(*point));
+                    nodes.len() - 1
+                },
+            };
+            path.push(index);
+        }
+
+        Some(path)
+    }
+
+    /// Write the record to text in SGF format.
+    /// If the board is larger than `MAX_SGF_SIZE`, return a result with error of type `RecordError`.
+    pub fn to_sgf(&self) -> Result<String, RecordError> {
+        if self.size > MAX_SGF_SIZE {
+            return Err(RecordError::BoardTooLarge(self.size));
+        }
+
+        let mut sgf = format!("(;GM[{}]FF[4]SZ[{}]", SGF_GAME_TYPE, self.size);
+        for (ident, value) in rule_properties(&self.rules) {
+            sgf.push_str(&format!("{}[{}]", ident, value));
+        }
+
+        let metadata = &self.metadata;
+        let properties = [
+            ("PB", &metadata.black),
+            ("PW", &metadata.white),
+            ("DT", &metadata.date),
+            ("EV", &metadata.event),
+            ("RU", &metadata.rules),
+            ("RE", &metadata.result),
+            ("C", &self.comment),
+        ];
+        for (ident, value) in properties.iter() {
+            if let Some(value) = value {
+                sgf.push_str(&format!("{}[{}]", ident, escape_text(value)));
+            }
+        }
+
+        write_variations(&mut sgf, &self.variations, &Game::with_rules(self.size, self.rules), 0);
+        sgf.push(')');
+
+        Ok(sgf)
+    }
+}
+
+// Private methods
+impl GameRecord {
+    /// Create a record from a parsed SGF tree, validating every move.
+    fn from_sgf_tree(tree: SgfNode) -> Result<GameRecord, RecordError> {
+        if let Some(game_type) = tree.property("GM") {
+            if game_type != SGF_GAME_TYPE {
+                return Err(RecordError::UnsupportedGame);
+            }
+        }
+
+        let size = match tree.property("SZ") {
+            Some(size) => size.parse()
+                .ok()
+                .filter(|size| (1..=MAX_SGF_SIZE).contains(size))
+                .ok_or_else(|| RecordError::InvalidProperty(String::from("SZ")))?,
+            None => 15,
+        };
+
+        let rules = read_rules(&tree)?;
+        let mut record = GameRecord::with_rules(size, rules);
+        record.metadata = RecordMetadata {
+            black: tree.property("PB").map(String::from),
+            white: tree.property("PW").map(String::from),
+            date: tree.property("DT").map(String::from),
+            event: tree.property("EV").map(String::from),
+            rules: tree.property("RU").map(String::from),
+            result: tree.property("RE").map(String::from),
+        };
+        record.comment = tree.property("C").map(String::from);
+
+        let game = Game::with_rules(size, rules);
+        record.variations = tree.children.into_iter()
+            .map(|child| read_node(child, &game))
+            .collect::<Result<_, _>>()?;
+
+        Ok(record)
+    }
+}
+
+/// Get the SGF properties of the rules that differ from the default rules.
+fn rule_properties(rules: &GameRules) -> Vec<(&'static str, String)> {
+    let default = GameRules::default();
+    let mut properties = vec![];
+
+    if rules.geometry != default.geometry {
+        properties.push(("GE", String::from(rules.geometry.name())));
+    }
+    if rules.rule_set != default.rule_set {
+        properties.push(("RS", String::from(rules.rule_set.name())));
+    }
+    if rules.connect != default.connect {
+        properties.push(("CN", rules.connect.to_string()));
+    }
+    if rules.early_draw != default.early_draw {
+        properties.push(("ED", String::from(if rules.early_draw { "1" } else { "0" })));
+    }
+    if rules.first_turn_stones != default.first_turn_stones {
+        properties.push(("FS", rules.first_turn_stones.to_string()));
+    }
+    if rules.stones_per_turn != default.stones_per_turn {
+        properties.push(("NS", rules.stones_per_turn.to_string()));
+    }
+
+    properties
+}
+
+/// Read the rules from the first node of a parsed SGF tree, with the default for every rule
+/// that is not given.
+fn read_rules(tree: &SgfNode) -> Result<GameRules, RecordError> {
+    let invalid = |ident: &str| RecordError::InvalidProperty(String::from(ident));
+    let number = |ident: &str| -> Result<Option<usize>, RecordError> {
+        tree.property(ident)
+            .map(|value| value.parse().ok().filter(|&n| n > 0).ok_or_else(|| invalid(ident)))
+            .transpose()
+    };
+
+    let mut rules = GameRules::default();
+    if let Some(name) = tree.property("GE") {
+        rules.geometry = BoardGeometry::from_name(name).ok_or_else(|| invalid("GE"))?;
+    }
+    if let Some(name) = tree.property("RS") {
+        rules.rule_set = RuleSet::from_name(name).ok_or_else(|| invalid("RS"))?;
+    }
+    if let Some(connect) = number("CN")? {
+        rules.connect = connect;
+    }
+    if let Some(value) = tree.property("ED") {
+        rules.early_draw = match value {
+            "1" => true,
+            "0" => false,
+            _ => return Err(invalid("ED")),
+        };
+    }
+    if let Some(stones) = number("FS")? {
+        rules.first_turn_stones = stones;
+    }
+    if let Some(stones) = number("NS")? {
+        rules.stones_per_turn = stones;
+    }
+
+    Ok(rules)
+}
+
+/// Write moves and their following moves to sgf, in the format of SGF variations.
+/// The moves are the step with index in a game from start.
+fn write_variations(sgf: &mut String, nodes: &[RecordNode], start: &Game, index: usize) {
+    match nodes {
+        [] => (),
+        [node] => write_node(sgf, node, start, index),
+        _ => {
+            for node in nodes {
+                sgf.push('(');
+                write_node(sgf, node, start, index);
+                sgf.push(')');
+            }
+        },
+    }
+}
+
+/// Write a move and its following moves to sgf, where the move is the step with index
+/// in a game from start. The following stones of the same turn are written in the same node,
+/// unless a stone before them has variations, annotations or a comment.
+fn write_node(sgf: &mut String, node: &RecordNode, start: &Game, index: usize) {
+    // Records only hold games of black and white, so every other side is white
+    let side = start.turn_order()[start.turn_of(index) % start.players()];
+    let ident = if side == GameSide::Black { "B" } else { "W" };
+    sgf.push_str(&format!(";{}[{}]", ident, point_to_sgf(&node.point)));
+
+    let (mut node, mut index) = (node, index);
+    while !start.ends_turn(index) && node.annotations.is_empty() && node.comment.is_none() {
+        match &node.children[..] {
+            [child] => {
+                sgf.push_str(&format!("[{}]", point_to_sgf(&child.point)));
+                node = child;
+                index += 1;
+            },
+            _ => break,
+        }
+    }
+
+    for annotation in &node.annotations {
+        sgf.push_str(&format!("{}[{}]", annotation.property(), annotation.value()));
+    }
+    if let Some(comment) = &node.comment {
+        sgf.push_str(&format!("C[{}]", escape_text(comment)));
+    }
+
+    write_variations(sgf, &node.children, start, index + 1);
+}
+
+/// Create a record node from a parsed SGF node, played on game.
+/// A node with more than one stone of a turn is read as a line of record nodes,
+/// with the annotations, comment and following moves on the last of them.
+fn read_node(sgf_node: SgfNode, game: &Game) -> Result<RecordNode, RecordError> {
+    let (side, ident) = match (sgf_node.property("B"), sgf_node.property("W")) {
+        (Some(_), None) => (GameSide::Black, "B"),
+        (None, Some(_)) => (GameSide::White, "W"),
+        _ => return Err(RecordError::InvalidProperty(String::from("B"))),
+    };
+    let points = sgf_node.properties.iter()
+        .filter(|(id, _)| id == ident)
+        .map(|(_, value)| point_from_sgf(value).ok_or_else(|| RecordError::InvalidProperty(String::from(ident))))
+        .collect::<Result<Vec<Point>, _>>()?;
+
+    let mut game = game.clone();
+    for point in &points {
+        if side != game.side {
+            return Err(RecordError::WrongSide(*point));
+        }
+        game.add_step(*point).map_err(|err| RecordError::InvalidStep(*point, err))?;
+    }
+
+    let annotations = sgf_node.properties.iter()
+        .filter_map(|(ident, _)| MoveAnnotation::from_property(ident))
+        .collect();
+    let comment = sgf_node.property("C").map(String::from);
+    let children = sgf_node.children.into_iter()
+        .map(|child| read_node(child, &game))
+        .collect::<Result<_, _>>()?;
+
+    let (last, rest) = points.split_last()
+        .ok_or_else(|| RecordError::InvalidProperty(String::from(ident)))?;
+    let mut node = RecordNode {
+        point: *last,
+        comment,
+        annotations,
+        children,
+    };
+    for point in rest.iter().rev() {
+        node = RecordNode {
+            children: vec![node],
+            ..RecordNode::new(*point)
+        };
+    }
+
+    Ok(node)
+}
+
+/// Convert a coordinate to its SGF letter.
+fn coord_to_sgf(coord: usize) -> char {
+    let coord = coord as u8;
+    if coord < 26 {
+        (b'a' + coord) as char
+    } else {
+        (b'A' + coord - 26) as char
+    }
+}
+
+/// Convert an SGF letter to its coordinate, if valid.
+fn coord_from_sgf(chr: char) -> Option<usize> {
+    match chr {
+        'a'..='z' => Some(chr as usize - 'a' as usize),
+        'A'..='Z' => Some(chr as usize - 'A' as usize + 26),
+        _ => None,
+    }
+}
+
+/// Convert a point to its SGF representation, e.g. `Point(7, 8)` to `"hi"`.
+/// The layer is added as a third letter if it is not 0, e.g. `Point(7, 8, 2)` to `"hic"`.
+pub(crate) fn point_to_sgf(point: &Point) -> String {
+    let mut text: String = [coord_to_sgf(point.x), coord_to_sgf(point.y)].iter().collect();
+    if point.z > 0 {
+        text.push(coord_to_sgf(point.z));
+    }
+    text
+}
+
+/// Convert an SGF representation of a point to the point, if valid.
+pub(crate) fn point_from_sgf(text: &str) -> Option<Point> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next(), chars.next(), chars.next()) {
+        (Some(x), Some(y), None, None) => Some(Point::new(coord_from_sgf(x)?, coord_from_sgf(y)?)),
+        (Some(x), Some(y), Some(z), None) => {
+            Some(Point::with_z(coord_from_sgf(x)?, coord_from_sgf(y)?, coord_from_sgf(z)?))
+        },
+        _ => None,
+    }
+}
+
+/// Format points as a string of SGF coordinates, e.g. `"hhig"`.
+/// The coordinates of the points are expected to be less than `MAX_SGF_SIZE`,
+/// and the points to be on the first layer.
+pub fn format_sgf_points(points: &[Point]) -> String {
+    points.iter().map(point_to_sgf).collect()
+}
+
+/// Parse a string of SGF coordinates, e.g. `"hhig"`, into points.
+/// Return `None` if the string is not made of pairs of valid coordinates.
+pub fn parse_sgf_points(text: &str) -> Option<Vec<Point>> {
+    let chars: Vec<char> = text.trim().chars().collect();
+    if !chars.len().is_multiple_of(2) {
+        return None;
+    }
+
+    chars.chunks(2)
+        .map(|pair| Some(Point::new(coord_from_sgf(pair[0])?, coord_from_sgf(pair[1])?)))
+        .collect()
+}
+
+/// Escape the characters of text that are special inside an SGF property value.
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for chr in text.chars() {
+        if chr == ']' || chr == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(chr);
+    }
+    escaped
+}
+
+/// A node of a parsed SGF game tree, before its properties are interpreted.
+struct SgfNode {
+    /// The properties of the node, as pairs of identifier and value.
+    properties: Vec<(String, String)>,
+    /// The nodes following this node.
+    children: Vec<SgfNode>,
+}
+
+impl SgfNode {
+    /// Get the first value of a property, if it exists.
+    fn property(&self, ident: &str) -> Option<&str> {
+        self.properties.iter()
+            .find(|(id, _)| id == ident)
+            .map(|(_, value)| &value[..])
+    }
+}
+
+/// A recursive descent parser for the SGF format.
+struct SgfParser<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+impl<'a> SgfParser<'a> {
+    fn new(text: &'a str) -> SgfParser<'a> {
+        SgfParser {
+            text,
+            offset: 0,
+        }
+    }
+
+    /// Skip whitespace, and return true if there is text remaining.
+    fn skip_whitespace(&mut self) -> bool {
+        let rest = &self.text[self.offset..];
+        let trimmed = rest.trim_start();
+        self.offset += rest.len() - trimmed.len();
+        !trimmed.is_empty()
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.text[self.offset..].chars().next()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), RecordError> {
+        if self.peek() == Some(expected) {
+            self.offset += expected.len_utf8();
+            Ok(())
+        } else {
+            Err(RecordError::Syntax(self.offset))
+        }
+    }
+
+    /// Parse a game tree, in the form `(;node;node(variation)(variation))`.
+    /// Return the first node of the sequence, with the rest of the sequence as its descendants.
+    fn parse_tree(&mut self) -> Result<SgfNode, RecordError> {
+        self.expect('(')?;
+
+        let mut sequence = vec![];
+        while self.peek() == Some(';') {
+            sequence.push(self.parse_node()?);
+        }
+        if sequence.is_empty() {
+            return Err(RecordError::Syntax(self.offset));
+        }
+
+        let mut variations = vec![];
+        while self.peek() == Some('(') {
+            variations.push(self.parse_tree()?);
+        }
+        self.expect(')')?;
+
+        // Link the sequence from the back, with the variations following the last node
+        let mut children = variations;
+        while let Some(mut node) = sequence.pop() {
+            node.children = children;
+            children = vec![node];
+        }
+
+        Ok(children.remove(0))
+    }
+
+    /// Parse a node, in the form `;ID[value]ID[value][value]`.
+    fn parse_node(&mut self) -> Result<SgfNode, RecordError> {
+        self.expect(';')?;
+
+        let mut properties = vec![];
+        while let Some(chr) = self.peek() {
+            if !chr.is_ascii_uppercase() {
+                break;
+            }
+
+            let start = self.offset;
+            let ident_len = self.text[start..]
+                .find(|c: char| !c.is_ascii_uppercase())
+                .unwrap_or(self.text.len() - start);
+            let ident = String::from(&self.text[start..start + ident_len]);
+            self.offset += ident_len;
+
+            if self.peek() != Some('[') {
+                return Err(RecordError::Syntax(self.offset));
+            }
+            while self.peek() == Some('[') {
+                let value = self.parse_value()?;
+                properties.push((ident.clone(), value));
+            }
+        }
+
+        Ok(SgfNode {
+            properties,
+            children: vec![],
+        })
+    }
+
+    /// Parse a property value, in the form `[value]`, unescaping special characters.
+    fn parse_value(&mut self) -> Result<String, RecordError> {
+        self.expect('[')?;
+
+        let mut value = String::new();
+        let mut chars = self.text[self.offset..].char_indices();
+        while let Some((i, chr)) = chars.next() {
+            match chr {
+                ']' => {
+                    self.offset += i + 1;
+                    return Ok(value);
+                },
+                '\\' => match chars.next() {
+                    Some((_, escaped)) => value.push(escaped),
+                    None => break,
+                },
+                _ => value.push(chr),
+            }
+        }
+
+        Err(RecordError::Syntax(self.text.len()))
+    }
+}
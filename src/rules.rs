@@ -0,0 +1,302 @@
+use crate::{BoardGeometry, Game, GameSide, GameSpot, Point};
+
+/// The number of consecutive pieces needed to win when no other value is given.
+static DEFAULT_CONNECT: usize = 5;
+
+/// The number of consecutive pieces needed to win in Connect6.
+static CONNECT6_CONNECT: usize = 6;
+
+/// The set of rules that decides which points may be played and which rows win.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum RuleSet {
+    /// Any row of five or more pieces wins, and every empty point may be played.
+    Freestyle,
+
+    /// Black may not play a point that makes a double three, a double four or an overline,
+    /// unless it also makes five in a row, and only wins with exactly five in a row.
+    /// Five stands for the winning length of the rules, if it is set to another value.
+    /// White plays as in freestyle.
+    ///
+    /// Whether a three can become a straight four is decided without checking if the
+    /// point that makes the four is itself forbidden.
+    Renju,
+
+    /// Any row long enough to win under the other rule sets instead loses for the side that
+    /// makes it, and every empty point may be played. The threat solvers do not support these rules.
+    Misere,
+}
+
+impl RuleSet {
+    /// All the rule sets.
+    pub const ALL: [RuleSet; 3] = [RuleSet::Freestyle, RuleSet::Renju, RuleSet::Misere];
+
+    /// Get the name of the rule set, as used in settings.
+    pub fn name(&self) -> &'static str {
+        match self {
+            RuleSet::Freestyle => "freestyle",
+            RuleSet::Renju => "renju",
+            RuleSet::Misere => "misere",
+        }
+    }
+
+    /// Get the rule set with a name, if any.
+    pub fn from_name(name: &str) -> Option<RuleSet> {
+        RuleSet::ALL.iter().find(|rule_set| rule_set.name() == name).copied()
+    }
+}
+
+/// The rules a game is played under.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct GameRules {
+    /// The set of rules for forbidden points and winning rows.
+    pub rule_set: RuleSet,
+
+    /// The number of consecutive pieces needed to win, which is 5 unless set otherwise.
+    /// The built-in engines support lengths from 3 to 17.
+    pub connect: usize,
+
+    /// Whether the game ends in a draw as soon as neither side can complete a winning row
+    /// on any line, instead of only when the board is full. Off unless set otherwise.
+    pub early_draw: bool,
+
+    /// The number of stones placed in the first turn of the game, which is 1 unless set otherwise.
+    pub first_turn_stones: usize,
+
+    /// The number of stones placed in every turn after the first, which is 1 unless set otherwise.
+    /// The built-in engines and solvers only support games where every turn has one stone.
+    pub stones_per_turn: usize,
+
+    /// The shape of the cells of the board, which is square unless set otherwise.
+    pub geometry: BoardGeometry,
+}
+
+impl GameRules {
+    /// Create rules with a rule set, where five in a row wins.
+    pub fn new(rule_set: RuleSet) -> GameRules {
+        GameRules {
+            rule_set,
+            connect: DEFAULT_CONNECT,
+            early_draw: false,
+            first_turn_stones: 1,
+            stones_per_turn: 1,
+            geometry: BoardGeometry::Square,
+        }
+    }
+
+    /// Create freestyle rules where connect pieces in a row win.
+    pub fn with_connect(connect: usize) -> GameRules {
+        GameRules {
+            rule_set: RuleSet::Freestyle,
+            connect,
+            early_draw: false,
+            first_turn_stones: 1,
+            stones_per_turn: 1,
+            geometry: BoardGeometry::Square,
+        }
+    }
+
+    /// Create Connect6 rules, where black places one stone in the first turn,
+    /// then each side places two stones in every turn, and six in a row wins.
+    pub fn connect6() -> GameRules {
+        GameRules {
+            first_turn_stones: 1,
+            stones_per_turn: 2,
+            ..GameRules::with_connect(CONNECT6_CONNECT)
+        }
+    }
+
+    /// Return true if every turn of the game has exactly one stone, otherwise false.
+    pub fn has_single_stone_turns(&self) -> bool {
+        self.first_turn_stones <= 1 && self.stones_per_turn <= 1
+    }
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        GameRules::new(RuleSet::Freestyle)
+    }
+}
+
+/// The state of a spot on a line, from the view of one side.
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum Cell {
+    Own,
+    Empty,
+    /// A piece of the opponent, or outside the board.
+    Blocked,
+}
+
+// Rules
+impl Game {
+    /// Get the rules the game is played under.
+    pub fn rules(&self) -> &GameRules {
+        &self.rules
+    }
+
+    /// Return true if side placing a piece at point would make a row that wins, otherwise false.
+    /// The point is expected to be empty.
+    pub fn is_winning_step(&self, point: &Point, side: GameSide) -> bool {
+        self.directions().iter().any(|&direction| {
+            let run = 1 + self.count_own(point, direction, side) + self.count_own(point, (-direction.0, -direction.1, -direction.2), side);
+            self.is_winning_run(run, side)
+        })
+    }
+
+    /// Return true if point is forbidden for side by the rules, otherwise false.
+    /// The point is expected to be empty.
+    pub fn is_forbidden(&self, point: &Point, side: GameSide) -> bool {
+        if self.rules.rule_set != RuleSet::Renju || side != GameSide::Black {
+            return false;
+        }
+
+        let connect = self.rules.connect;
+        let mut overline = false;
+        let mut fours = 0;
+        let mut threes = 0;
+
+        for &direction in self.directions() {
+            let (cells, center) = self.line_cells(point, direction, side, connect + 1);
+            let run = run_length(&cells, center);
+
+            if run == connect {
+                // Making five always wins, even if it also makes a forbidden shape
+                return false;
+            } else if run > connect {
+                overline = true;
+            }
+
+            fours += count_fours(&cells, center, connect);
+            if is_open_three(&cells, center, connect) {
+                threes += 1;
+            }
+        }
+
+        overline || fours >= 2 || threes >= 2
+    }
+
+    /// Return the point at distance steps from point in direction, if it is on the board.
+    pub(crate) fn offset(&self, point: &Point, direction: (isize, isize, isize), distance: isize) -> Option<Point> {
+        let x = point.x as isize + direction.0 * distance;
+        let y = point.y as isize + direction.1 * distance;
+        let z = point.z as isize + direction.2 * distance;
+        let size = self.size() as isize;
+        let layers = self.geometry().layers(self.size()) as isize;
+
+        if (0..size).contains(&x) && (0..size).contains(&y) && (0..layers).contains(&z) {
+            Some(Point::with_z(x as usize, y as usize, z as usize))
+        } else {
+            None
+        }
+    }
+
+    /// Return the number of consecutive pieces of side next to point in direction, up to one
+    /// more than needed to win.
+    fn count_own(&self, point: &Point, direction: (isize, isize, isize), side: GameSide) -> usize {
+        (1..=self.rules.connect as isize)
+            .take_while(|&distance| {
+                self.offset(point, direction, distance).is_some_and(|p| self.spot(&p) == GameSpot::Taken(side))
+            })
+            .count()
+    }
+
+    /// Return true if a run of length pieces wins for side under the rules, otherwise false.
+    fn is_winning_run(&self, length: usize, side: GameSide) -> bool {
+        match (self.rules.rule_set, side) {
+            (RuleSet::Renju, GameSide::Black) => length == self.rules.connect,
+            _ => length >= self.rules.connect,
+        }
+    }
+
+    /// Return the cells on the line through point in direction, up to reach from point,
+    /// as if side placed a piece at point. Also return the index of point in the cells.
+    fn line_cells(&self, point: &Point, direction: (isize, isize, isize), side: GameSide, reach: usize) -> (Vec<Cell>, usize) {
+        let reach = reach as isize;
+        let cells = (-reach..=reach)
+            .map(|distance| {
+                if distance == 0 {
+                    return Cell::Own;
+                }
+                match self.offset(point, direction, distance).map(|p| self.spot(&p)) {
+                    Some(GameSpot::Empty) => Cell::Empty,
+                    Some(GameSpot::Taken(s)) if s == side => Cell::Own,
+                    _ => Cell::Blocked,
+                }
+            })
+            .collect();
+
+        (cells, reach as usize)
+    }
+}
+
+/// Return the number of consecutive own cells through index.
+fn run_length(cells: &[Cell], index: usize) -> usize {
+    let before = cells[..index].iter().rev().take_while(|&&c| c == Cell::Own).count();
+    let after = cells[index + 1..].iter().take_while(|&&c| c == Cell::Own).count();
+    before + 1 + after
+}
+
+/// Return the empty cells that would make exactly five in a row through center.
+fn five_points(cells: &[Cell], center: usize, connect: usize) -> Vec<usize> {
+    let mut cells = cells.to_vec();
+    let mut points = vec![];
+
+    for i in 0..cells.len() {
+        if cells[i] != Cell::Empty {
+            continue;
+        }
+
+        cells[i] = Cell::Own;
+        let (start, end) = (i.min(center), i.max(center));
+        if run_length(&cells, i) == connect && cells[start..=end].iter().all(|&c| c == Cell::Own) {
+            points.push(i);
+        }
+        cells[i] = Cell::Empty;
+    }
+
+    points
+}
+
+/// Return the number of fours through center, where a four is a shape that can become
+/// exactly five with one more piece.
+fn count_fours(cells: &[Cell], center: usize, connect: usize) -> usize {
+    let points = five_points(cells, center, connect);
+
+    if is_straight_four(cells, &points, connect) {
+        // Both ends of four in a row are the same four
+        1
+    } else {
+        points.len()
+    }
+}
+
+/// Return true if the points that make five are both ends of four in a row, otherwise false.
+fn is_straight_four(cells: &[Cell], points: &[usize], connect: usize) -> bool {
+    match *points {
+        [a, b] => b - a == connect && cells[a + 1..b].iter().all(|&c| c == Cell::Own),
+        _ => false,
+    }
+}
+
+/// Return true if there is an open three through center, which is a shape that can become
+/// a straight four (four in a row with both ends open for five) with one more piece.
+fn is_open_three(cells: &[Cell], center: usize, connect: usize) -> bool {
+    let mut cells = cells.to_vec();
+
+    for i in 0..cells.len() {
+        if cells[i] != Cell::Empty {
+            continue;
+        }
+
+        cells[i] = Cell::Own;
+        let points = five_points(&cells, center, connect);
+        // The straight four must be made by the new piece, and not already be on the line
+        let straight = is_straight_four(&cells, &points, connect) && points[0] < i && i < points[1];
+        cells[i] = Cell::Empty;
+
+        if straight {
+            return true;
+        }
+    }
+
+    false
+}
@@ -0,0 +1,66 @@
+//! Solvers that search for forced wins in a game.
+
+mod pns;
+mod vcf;
+mod vct;
+
+pub use pns::*;
+pub use vcf::*;
+pub use vct::*;
+
+use crate::{Game, GameSide, GameSpot, Point, RuleSet};
+use crate::engine::{Pattern, is_supported, pattern_at};
+
+/// Return true if the solvers can search game, otherwise false.
+/// Besides what the engines need, the threats only lead to a win when making a row wins,
+/// so games under misère rules are not solvable.
+pub fn is_solvable(game: &Game) -> bool {
+    is_supported(game) && game.rules().rule_set != RuleSet::Misere
+}
+
+/// Return the points on the board that are on a line through point, close enough to make five with it.
+fn lines_through(game: &Game, point: &Point) -> Vec<Point> {
+    let reach = game.rules().connect as isize - 1;
+    game.directions().iter()
+        .flat_map(|&direction| (-reach..=reach).filter_map(move |distance| game.offset(point, direction, distance)))
+        .collect()
+}
+
+/// Return the points among points where side would make five that wins, in order.
+fn five_points<'a>(game: &Game, points: impl Iterator<Item=&'a Point>, side: GameSide) -> Vec<Point> {
+    points
+        .filter(|p| game.spot(p).is_empty() && game.is_winning_step(p, side))
+        .cloned()
+        .collect()
+}
+
+/// Return the empty points that are not forbidden for side, where side would make a pattern
+/// accepted by filter in any direction, in the order of the board.
+fn pattern_points<F>(game: &Game, side: GameSide, filter: F) -> Vec<Point>
+    where F: Fn(Pattern) -> bool
+{
+    // Patterns can only be made on lines through the pieces of side. The pieces are read from
+    // the board, since the solvers may search for a side that is not the one the steps give
+    let size = game.size();
+    let mut near = vec![false; size * size];
+    for piece in game.iter_points().filter(|p| game.spot(p) == GameSpot::Taken(side)) {
+        for point in lines_through(game, piece) {
+            near[point.y * size + point.x] = true;
+        }
+    }
+
+    game.iter_points()
+        .filter(|p| {
+            near[p.y * size + p.x]
+                && game.spot(p).is_empty()
+                && game.directions().iter().any(|&direction| pattern_at(game, p, direction, side).is_some_and(&filter))
+                && !game.is_forbidden(p, side)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Return the points where side would make a four, in the order of the board.
+fn four_points(game: &Game, side: GameSide) -> Vec<Point> {
+    pattern_points(game, side, |pattern| pattern.is_four())
+}
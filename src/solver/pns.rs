@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use crate::{Game, GameSide, GameState, Point};
+use crate::hash::piece_key;
+use crate::solver::is_solvable;
+
+/// The number of positions searched when no other value is given.
+static DEFAULT_MAX_NODES: u64 = 1_000_000;
+
+/// The proof or disproof number of a position that is solved.
+static INFINITY: u64 = u64::MAX;
+
+/// The game-theoretic value of a position for the side to move.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum GameValue {
+    Win,
+    Draw,
+    Loss,
+}
+
+/// The result of a proof-number search.
+#[derive(PartialEq, Clone, Debug)]
+pub enum PnsResult {
+    /// The value of the position is proven. Contains a step that keeps the value,
+    /// or `None` if the position is lost or there are no steps left.
+    Proven {
+        value: GameValue,
+        best_step: Option<Point>,
+    },
+
+    /// The node budget ran out before the value was proven.
+    Unknown,
+}
+
+/// A solver that proves the value of a position with depth-first proof-number search,
+/// which is practical on small boards such as 7x7 to 9x9.
+///
+/// Every position is stored in a transposition table keyed by its position hash,
+/// with a proof number and a disproof number from the view of the side to move.
+#[derive(Clone, Debug)]
+pub struct PnsSolver {
+    max_nodes: u64,
+    nodes: u64,
+    aborted: bool,
+    /// The side that tries to win in the current search. The other side tries not to lose.
+    attacker: GameSide,
+    /// The proof and disproof numbers of searched positions.
+    table: HashMap<u64, (u64, u64)>,
+}
+
+// Initializers
+impl PnsSolver {
+    /// Create a new solver that searches at most max_nodes positions.
+    pub fn new(max_nodes: u64) -> PnsSolver {
+        PnsSolver {
+            max_nodes,
+            nodes: 0,
+            aborted: false,
+            attacker: GameSide::Black,
+            table: HashMap::new(),
+        }
+    }
+}
+
+impl Default for PnsSolver {
+    fn default() -> Self {
+        PnsSolver::new(DEFAULT_MAX_NODES)
+    }
+}
+
+// Public methods
+impl PnsSolver {
+    /// Get the number of positions searched by the last call to `solve`.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Get the number of positions in the transposition table.
+    pub fn table_len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Prove the value of game for the side to move.
+    ///
+    /// First try to prove that the side to move wins, and otherwise that the opponent wins.
+    /// If neither is possible, the position is a draw.
+    /// Games not supported by the engines, as decided by `engine::is_supported`, and games under
+    /// misère rules are not searched, and their result is unknown.
+    pub fn solve(&mut self, game: &Game) -> PnsResult {
+        self.nodes = 0;
+        self.aborted = false;
+
+        if !is_solvable(game) {
+            return PnsResult::Unknown;
+        }
+
+        let mut game = game.clone();
+        let side = game.side();
+
+        match self.prove(&mut game, side) {
+            Some(true) => return PnsResult::Proven {
+                value: GameValue::Win,
+                best_step: self.proving_step(&game),
+            },
+            Some(false) => (),
+            None => return PnsResult::Unknown,
+        }
+
+        match self.prove(&mut game, side.toggle()) {
+            Some(true) => PnsResult::Proven {
+                value: GameValue::Loss,
+                best_step: None,
+            },
+            Some(false) => PnsResult::Proven {
+                value: GameValue::Draw,
+                best_step: self.proving_step(&game),
+            },
+            None => PnsResult::Unknown,
+        }
+    }
+}
+
+// Private methods
+impl PnsSolver {
+    /// Search until it is proven or disproven that attacker wins in game.
+    /// Return `None` if the search was aborted.
+    fn prove(&mut self, game: &mut Game, attacker: GameSide) -> Option<bool> {
+        self.attacker = attacker;
+        self.table.clear();
+
+        let (proof, disproof) = self.search(game, INFINITY, INFINITY);
+        let root_wins = game.side() == attacker;
+
+        if proof == 0 {
+            Some(root_wins)
+        } else if disproof == 0 {
+            Some(!root_wins)
+        } else {
+            None
+        }
+    }
+
+    /// Return the step from game whose position is disproven for the side to move after it,
+    /// which is the step that proves the position.
+    /// A step that wins at once is returned first, as the steps after such a position are not searched.
+    fn proving_step(&self, game: &Game) -> Option<Point> {
+        let side = game.side();
+        let winning = game.iter_points()
+            .find(|p| game.spot(p).is_empty() && game.is_winning_step(p, side));
+        if let Some(point) = winning {
+            return Some(*point);
+        }
+
+        let hash = game.position_hash();
+        self.steps(game).into_iter()
+            .find(|step| self.numbers(hash ^ piece_key(step, side)).1 == 0)
+    }
+
+    /// Search game until its proof number reaches proof_limit or its disproof number
+    /// reaches disproof_limit, both from the view of the side to move.
+    /// Return the proof and disproof numbers.
+    fn search(&mut self, game: &mut Game, proof_limit: u64, disproof_limit: u64) -> (u64, u64) {
+        let hash = game.position_hash();
+        if self.nodes >= self.max_nodes {
+            self.aborted = true;
+            return self.numbers(hash);
+        }
+        self.nodes += 1;
+
+        if let Some(numbers) = self.terminal(game) {
+            self.table.insert(hash, numbers);
+            return numbers;
+        }
+
+        let side = game.side();
+        let steps = self.steps(game);
+
+        loop {
+            // The side to move needs one step that disproves the opponent,
+            // and the opponent must be proven after every step
+            let mut proof = INFINITY;
+            let mut disproof: u64 = 0;
+            let mut best = 0;
+            let mut second_proof = INFINITY;
+
+            for (i, step) in steps.iter().enumerate() {
+                let (child_proof, child_disproof) = self.numbers(hash ^ piece_key(step, side));
+                disproof = disproof.saturating_add(child_proof);
+                if child_disproof < proof {
+                    second_proof = proof;
+                    proof = child_disproof;
+                    best = i;
+                } else if child_disproof < second_proof {
+                    second_proof = child_disproof;
+                }
+            }
+
+            if proof >= proof_limit || disproof >= disproof_limit || self.aborted {
+                self.table.insert(hash, (proof, disproof));
+                return (proof, disproof);
+            }
+
+            let (child_proof, _) = self.numbers(hash ^ piece_key(&steps[best], side));
+            let child_proof_limit = if disproof_limit == INFINITY {
+                INFINITY
+            } else {
+                disproof_limit - disproof + child_proof
+            };
+            let child_disproof_limit = proof_limit.min(second_proof.saturating_add(1));
+
+            game.add_step(steps[best]).expect("steps of the search should be valid");
+            self.search(game, child_proof_limit, child_disproof_limit);
+            game.undo_step();
+        }
+    }
+
+    /// Return the proof and disproof numbers of the position with hash,
+    /// which are both 1 if it has not been searched.
+    fn numbers(&self, hash: u64) -> (u64, u64) {
+        self.table.get(&hash).cloned().unwrap_or((1, 1))
+    }
+
+    /// Return the proof and disproof numbers of game if its value is known without searching.
+    fn terminal(&self, game: &Game) -> Option<(u64, u64)> {
+        let side = game.side();
+        let solved = match game.state() {
+            // The previous step won, so the side to move can neither win nor avoid losing
+            GameState::Finished { .. } => false,
+            GameState::BoardFull | GameState::NoWinPossible => side != self.attacker,
+            GameState::Normal => {
+                let wins = game.iter_points()
+                    .any(|p| game.spot(p).is_empty() && game.is_winning_step(p, side));
+                if !wins {
+                    return None;
+                }
+                true
+            },
+        };
+
+        Some(if solved { (0, INFINITY) } else { (INFINITY, 0) })
+    }
+
+    /// Return the steps searched from game. If the opponent threatens to win,
+    /// only the steps that block them are searched.
+    fn steps(&self, game: &Game) -> Vec<Point> {
+        let side = game.side();
+        let size = game.size();
+        let center = (size / 2) as isize;
+        let mut steps: Vec<Point> = game.iter_points()
+            .filter(|p| game.spot(p).is_empty() && !game.is_forbidden(p, side))
+            .cloned()
+            .collect();
+
+        let blocks: Vec<Point> = steps.iter()
+            .filter(|p| game.is_winning_step(p, side.toggle()))
+            .cloned()
+            .collect();
+        if !blocks.is_empty() {
+            return blocks;
+        }
+
+        // Search the center first, where steps are usually stronger
+        steps.sort_by_key(|p| (p.x as isize - center).abs().max((p.y as isize - center).abs()));
+        steps
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashSet;
+use crate::{Game, GameSide, GameState, Point};
+use crate::solver::{five_points, four_points, is_solvable, lines_through};
+
+/// The number of positions searched when no other value is given.
+static DEFAULT_MAX_NODES: u64 = 100_000;
+
+/// The result of a VCF search.
+#[derive(PartialEq, Clone, Debug)]
+pub enum VcfResult {
+    /// The side wins by continuous fours. Contains the steps of both sides in order,
+    /// starting and ending with a step of the winning side.
+    ///
+    /// If the opponent can not block a four because the point is forbidden,
+    /// the steps end with that four.
+    Win(Vec<Point>),
+
+    /// There is no win by continuous fours.
+    NoWin,
+
+    /// The node budget ran out before the search was complete.
+    Unknown,
+}
+
+/// A solver that searches for a victory by continuous fours (VCF), where every step of
+/// the attacking side makes a four, so the opponent only ever has one reply.
+#[derive(Clone, Debug)]
+pub struct VcfSolver {
+    max_nodes: u64,
+    nodes: u64,
+    aborted: bool,
+    /// Hashes of positions already known to have no win.
+    failed: HashSet<u64>,
+}
+
+// Initializers
+impl VcfSolver {
+    /// Create a new solver that searches at most max_nodes positions.
+    pub fn new(max_nodes: u64) -> VcfSolver {
+        VcfSolver {
+            max_nodes,
+            nodes: 0,
+            aborted: false,
+            failed: HashSet::new(),
+        }
+    }
+}
+
+impl Default for VcfSolver {
+    fn default() -> Self {
+        VcfSolver::new(DEFAULT_MAX_NODES)
+    }
+}
+
+// Public methods
+impl VcfSolver {
+    /// Get the number of positions searched by the last call to `solve`.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Search for a win by continuous fours for side in game, as if side were to move.
+    /// Games not supported by the engines, as decided by `engine::is_supported`, and games under
+    /// misère rules are not searched, and their result is unknown.
+    pub fn solve(&mut self, game: &Game, side: GameSide) -> VcfResult {
+        self.nodes = 0;
+        self.aborted = false;
+        self.failed.clear();
+
+        if !is_solvable(game) {
+            return VcfResult::Unknown;
+        }
+
+        match game.state() {
+            GameState::Normal => (),
+            GameState::Finished { winner_side, .. } if *winner_side == side => return VcfResult::Win(vec![]),
+            _ => return VcfResult::NoWin,
+        }
+
+        let mut game = game.clone();
+        game.side = side;
+
+        let threats = five_points(&game, game.iter_points(), side.toggle());
+        match self.search(&mut game, threats) {
+            Some(steps) => VcfResult::Win(steps),
+            None if self.aborted => VcfResult::Unknown,
+            None => VcfResult::NoWin,
+        }
+    }
+}
+
+// Private methods
+impl VcfSolver {
+    /// Search for a win for the side to move, given the points where the opponent would make five.
+    /// Return the winning steps, or `None` if there are none or the search was aborted.
+    fn search(&mut self, game: &mut Game, threats: Vec<Point>) -> Option<Vec<Point>> {
+        if self.nodes >= self.max_nodes {
+            self.aborted = true;
+            return None;
+        }
+        self.nodes += 1;
+
+        let side = game.side();
+        let attacks = five_points(game, game.iter_points(), side);
+        if let Some(&point) = attacks.first() {
+            return Some(vec![point]);
+        }
+
+        let hash = game.position_hash();
+        if self.failed.contains(&hash) {
+            return None;
+        }
+
+        for point in four_points(game, side) {
+            // A four does not stop the opponent from making five, unless it is on their point
+            if threats.iter().any(|threat| *threat != point) {
+                continue;
+            }
+            if game.add_step(point).is_err() {
+                continue;
+            }
+
+            let fives = five_points(game, lines_through(game, &point).iter(), side);
+            let result = match fives[..] {
+                [] => None,
+                [block] => self.defend(game, point, block),
+                [block, win, ..] => Some(vec![point, block, win]),
+            };
+
+            game.undo_step();
+
+            if result.is_some() {
+                return result;
+            }
+            if self.aborted {
+                return None;
+            }
+        }
+
+        self.failed.insert(hash);
+        None
+    }
+
+    /// Play the only reply to the four made at point, which is block, and continue the search.
+    fn defend(&mut self, game: &mut Game, point: Point, block: Point) -> Option<Vec<Point>> {
+        if game.add_step(block).is_err() {
+            // The opponent is not allowed to block the four
+            return Some(vec![point]);
+        }
+
+        let result = match game.state() {
+            GameState::Normal => {
+                let threats = five_points(game, lines_through(game, &block).iter(), game.side().toggle());
+                self.search(game, threats).map(|steps| {
+                    let mut all = vec![point, block];
+                    all.extend(steps);
+                    all
+                })
+            },
+            _ => None,
+        };
+
+        game.undo_step();
+        result
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::fmt;
+use crate::{Game, GameRecord, GameSide, GameSpot, GameState, Point};
+use crate::engine::{Pattern, pattern_at};
+use crate::solver::{five_points, four_points, is_solvable, lines_through, pattern_points};
+
+/// The number of positions searched when no other value is given.
+static DEFAULT_MAX_NODES: u64 = 200_000;
+
+/// The number of steps of the attacking side searched when no other value is given.
+static DEFAULT_MAX_DEPTH: usize = 10;
+
+/// A winning step of the attacking side, with the winning answer to every defence.
+#[derive(PartialEq, Clone, Debug)]
+pub struct ThreatTree {
+    /// The point of the step.
+    pub point: Point,
+
+    /// Every reply of the opponent, each with the step that wins against it.
+    /// Empty if the step makes five, or if no reply of the opponent stops all of its threats.
+    pub defences: Vec<(Point, ThreatTree)>,
+}
+
+impl ThreatTree {
+    /// Create a tree for a step that needs no further steps to win.
+    fn leaf(point: Point) -> ThreatTree {
+        ThreatTree {
+            point,
+            defences: vec![],
+        }
+    }
+
+    /// Get the number of steps of the attacking side on the longest line of the tree.
+    pub fn depth(&self) -> usize {
+        1 + self.defences.iter().map(|(_, tree)| tree.depth()).max().unwrap_or(0)
+    }
+
+    /// Return the steps of both sides following the first defence at every step.
+    pub fn main_line(&self) -> Vec<Point> {
+        let mut steps = vec![self.point];
+        if let Some((defence, tree)) = self.defences.first() {
+            steps.push(*defence);
+            steps.extend(tree.main_line());
+        }
+        steps
+    }
+
+    /// Return the steps of both sides on every line of the tree, from the root to each leaf.
+    pub fn lines(&self) -> Vec<Vec<Point>> {
+        if self.defences.is_empty() {
+            return vec![vec![self.point]];
+        }
+
+        let mut lines = vec![];
+        for (defence, tree) in &self.defences {
+            for line in tree.lines() {
+                let mut steps = vec![self.point, *defence];
+                steps.extend(line);
+                lines.push(steps);
+            }
+        }
+        lines
+    }
+
+    /// Add every line of the tree to record, after the node at path.
+    /// The main line is added first, so it becomes the main variation if there are no moves yet.
+    ///
+    /// Return `None` if there is no node at path or the lines are invalid from there.
+    pub fn add_to_record(&self, record: &mut GameRecord, path: &[usize]) -> Option<()> {
+        for line in self.lines() {
+            record.add_line(path, &line)?;
+        }
+        Some(())
+    }
+
+    /// Write the tree with the given indentation, one step on each line.
+    fn write_indented(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        writeln!(f, "{:indent$}+ {} {}", "", self.point.x, self.point.y, indent = indent)?;
+        for (defence, tree) in &self.defences {
+            writeln!(f, "{:indent$}- {} {}", "", defence.x, defence.y, indent = indent + 2)?;
+            tree.write_indented(f, indent + 4)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ThreatTree {
+    /// Write the tree with steps of the attacking side marked by `+` and replies marked by `-`,
+    /// each indented below the step it follows.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_indented(f, 0)
+    }
+}
+
+/// The result of a VCT search.
+#[derive(PartialEq, Clone, Debug)]
+pub enum VctResult {
+    /// The side wins by continuous threats, following the tree.
+    Win(ThreatTree),
+
+    /// There is no win by continuous threats within the depth.
+    NoWin,
+
+    /// The node budget ran out before the search was complete.
+    Unknown,
+}
+
+/// A solver that searches for a victory by continuous threats (VCT), where every step of
+/// the attacking side makes a four or an open three.
+///
+/// The replies to a four are the points that block it. The replies to an open three are the
+/// points that stop it from becoming a straight four, along with any step that makes a four
+/// for the opponent. A step wins if the opponent has none of these replies, such as when it
+/// makes two open threes.
+#[derive(Clone, Debug)]
+pub struct VctSolver {
+    max_nodes: u64,
+    max_depth: usize,
+    nodes: u64,
+    aborted: bool,
+    /// Hashes of positions known to have no win, with the largest depth that was searched.
+    failed: HashMap<u64, usize>,
+}
+
+// Initializers
+impl VctSolver {
+    /// Create a new solver that searches at most max_nodes positions,
+    /// and at most max_depth steps of the attacking side on each line.
+    pub fn new(max_nodes: u64, max_depth: usize) -> VctSolver {
+        VctSolver {
+            max_nodes,
+            max_depth,
+            nodes: 0,
+            aborted: false,
+            failed: HashMap::new(),
+        }
+    }
+}
+
+impl Default for VctSolver {
+    fn default() -> Self {
+        VctSolver::new(DEFAULT_MAX_NODES, DEFAULT_MAX_DEPTH)
+    }
+}
+
+// Public methods
+impl VctSolver {
+    /// Get the number of positions searched by the last call to `solve`.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Search for a win by continuous threats for side in game, as if side were to move.
+    /// Games not supported by the engines, as decided by `engine::is_supported`, and games under
+    /// misère rules are not searched, and their result is unknown.
+    pub fn solve(&mut self, game: &Game, side: GameSide) -> VctResult {
+        self.nodes = 0;
+        self.aborted = false;
+        self.failed.clear();
+
+        if !is_solvable(game) {
+            return VctResult::Unknown;
+        }
+        if !matches!(game.state(), GameState::Normal) {
+            return VctResult::NoWin;
+        }
+
+        let mut game = game.clone();
+        game.side = side;
+
+        // Search deeper one step at a time, so the shortest win is found first
+        for depth in 1..=self.max_depth {
+            if let Some(tree) = self.search(&mut game, depth) {
+                return VctResult::Win(tree);
+            }
+            if self.aborted {
+                return VctResult::Unknown;
+            }
+        }
+
+        VctResult::NoWin
+    }
+}
+
+// Private methods
+impl VctSolver {
+    /// Search for a win for the side to move within depth steps.
+    /// Return the tree of the win, or `None` if there is none or the search was aborted.
+    fn search(&mut self, game: &mut Game, depth: usize) -> Option<ThreatTree> {
+        if self.nodes >= self.max_nodes {
+            self.aborted = true;
+            return None;
+        }
+        self.nodes += 1;
+
+        let side = game.side();
+        if let Some(&point) = five_points(game, game.iter_points(), side).first() {
+            return Some(ThreatTree::leaf(point));
+        }
+
+        let hash = game.position_hash();
+        if depth == 0 || self.failed.get(&hash).is_some_and(|&failed| failed >= depth) {
+            return None;
+        }
+
+        // A threat does not stop the opponent from making five, unless it is on their point
+        let threats = five_points(game, game.iter_points(), side.toggle());
+        let mut attacks = four_points(game, side);
+        attacks.extend(pattern_points(game, side, |pattern| pattern == Pattern::OpenThree)
+            .into_iter()
+            .filter(|p| !attacks.contains(p))
+            .collect::<Vec<Point>>());
+        attacks.retain(|p| threats.iter().all(|threat| threat == p));
+
+        for point in attacks {
+            if game.add_step(point).is_err() {
+                continue;
+            }
+
+            let result = match defences(game, &point) {
+                Some(defences) => self.defend(game, point, defences, depth),
+                None => Some(ThreatTree::leaf(point)),
+            };
+
+            game.undo_step();
+
+            if result.is_some() {
+                return result;
+            }
+            if self.aborted {
+                return None;
+            }
+        }
+
+        self.failed.insert(hash, depth);
+        None
+    }
+
+    /// Try every defence against the step at point, and return the tree if all of them lose.
+    fn defend(&mut self, game: &mut Game, point: Point, defences: Vec<Point>, depth: usize) -> Option<ThreatTree> {
+        let mut tree = ThreatTree::leaf(point);
+
+        for defence in defences {
+            if game.add_step(defence).is_err() {
+                // The opponent is not allowed to play there
+                continue;
+            }
+
+            let result = match game.state() {
+                GameState::Normal => self.search(game, depth - 1),
+                _ => None,
+            };
+
+            game.undo_step();
+
+            tree.defences.push((defence, result?));
+        }
+
+        Some(tree)
+    }
+}
+
+/// Return the replies of the opponent to the threat made by the step at point,
+/// or `None` if the threat can not be stopped by any reply.
+fn defences(game: &mut Game, point: &Point) -> Option<Vec<Point>> {
+    let side = game.side().toggle();
+    let fives = five_points(game, lines_through(game, point).iter(), side);
+
+    if !fives.is_empty() {
+        // Only blocking a four stops it, and the other point wins if there are two
+        return if fives.iter().any(|p| !game.is_forbidden(p, game.side())) {
+            Some(fives)
+        } else {
+            None
+        };
+    }
+
+    let candidates: Vec<Point> = lines_through(game, point).into_iter()
+        .filter(|p| game.spot(p).is_empty() && !game.is_forbidden(p, game.side()))
+        .fold(vec![], |mut points, p| {
+            if !points.contains(&p) {
+                points.push(p);
+            }
+            points
+        });
+
+    let mut stopping: Vec<Point> = candidates.into_iter()
+        .filter(|candidate| {
+            // Only the pieces matter here, so place the reply without adding a step
+            let defender = game.side();
+            game.board.set_spot(candidate, GameSpot::Taken(defender));
+            let stops = !has_threat(game, point, side);
+            game.board.set_spot(candidate, GameSpot::Empty);
+            stops
+        })
+        .collect();
+
+    // The opponent may also gain time by making a four
+    for four in four_points(game, game.side()) {
+        if !stopping.contains(&four) {
+            stopping.push(four);
+        }
+    }
+
+    if stopping.is_empty() {
+        None
+    } else {
+        Some(stopping)
+    }
+}
+
+/// Return true if side can make five or a straight four on the lines through point, otherwise false.
+fn has_threat(game: &Game, point: &Point, side: GameSide) -> bool {
+    let reach = game.rules().connect as isize - 1;
+
+    game.directions().iter().any(|&direction| {
+        (-reach..=reach)
+            .filter_map(|distance| game.offset(point, direction, distance))
+            .filter(|p| game.spot(p).is_empty())
+            .any(|p| game.is_winning_step(&p, side) || pattern_at(game, &p, direction, side) == Some(Pattern::OpenFour))
+    })
+}
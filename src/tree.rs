@@ -0,0 +1,302 @@
+use std::convert::TryFrom;
+
+use crate::{Game, GameRecord, GameRules, GameStepError, Point, RecordNode};
+
+/// An identifier of a node in a `GameTree`.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct NodeId(usize);
+
+/// A node in a game tree, where each node other than the root is a step.
+#[derive(Clone, Debug)]
+struct TreeNode {
+    /// The point of the step, or `None` for the root.
+    point: Option<Point>,
+    /// The parent of the node, or `None` for the root.
+    parent: Option<NodeId>,
+    /// The steps that may follow this node. The first one is the main line,
+    /// and the others are variations.
+    children: Vec<NodeId>,
+}
+
+/// A tree of game steps, used to explore alternative lines from any step
+/// without losing the main line.
+///
+/// The tree keeps track of a current node, which is where new steps are added.
+#[derive(Clone, Debug)]
+pub struct GameTree {
+    /// The game at the root, which holds the size, rules, players and blocked points
+    /// every step in the tree is played with.
+    start: Game,
+    /// All the nodes of the tree, with the root at index 0.
+    nodes: Vec<TreeNode>,
+    /// The current node of the tree.
+    current: NodeId,
+    /// The game at the current node.
+    game: Game,
+}
+
+// Initializers
+impl GameTree {
+    /// Create a new tree for a board with size, containing only the starting position.
+    pub fn new(size: usize) -> GameTree {
+        GameTree::with_rules(size, GameRules::default())
+    }
+
+    /// Create a new tree for a board with size played under rules,
+    /// containing only the starting position.
+    pub fn with_rules(size: usize, rules: GameRules) -> GameTree {
+        GameTree::from_start(Game::with_rules(size, rules))
+    }
+
+    /// Create a tree with the steps of a game as its main line,
+    /// with the current node at the last step.
+    pub fn from_game(game: &Game) -> GameTree {
+        let mut tree = GameTree::from_start(game.starting_position());
+
+        for (_, point) in game.iter_steps() {
+            tree.current = tree.add_child(*point);
+        }
+        tree.game = game.clone();
+
+        tree
+    }
+}
+
+impl TryFrom<&GameRecord> for GameTree {
+    type Error = GameStepError;
+
+    /// Create a tree with the same moves and variations as a record,
+    /// with the current node at the root.
+    fn try_from(record: &GameRecord) -> Result<GameTree, GameStepError> {
+        fn add_nodes(tree: &mut GameTree, nodes: &[RecordNode]) -> Result<(), GameStepError> {
+            let parent = tree.current;
+            for node in nodes {
+                tree.add_step(node.point)?;
+                add_nodes(tree, &node.children)?;
+                tree.go_to(parent);
+            }
+            Ok(())
+        }
+
+        let mut tree = GameTree::with_rules(record.size(), *record.rules());
+        add_nodes(&mut tree, &record.variations)?;
+
+        Ok(tree)
+    }
+}
+
+// Public methods
+impl GameTree {
+    /// Get the size of the board.
+    pub fn size(&self) -> usize {
+        self.start.size()
+    }
+
+    /// Get the rules the steps in the tree are played under.
+    pub fn rules(&self) -> &GameRules {
+        self.start.rules()
+    }
+
+    /// Get the root node, representing the starting position.
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    /// Get the current node.
+    pub fn current(&self) -> NodeId {
+        self.current
+    }
+
+    /// Get the game at the current node.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Get the point of the step at node, or `None` for the root.
+    pub fn point(&self, id: NodeId) -> Option<Point> {
+        self.nodes[id.0].point
+    }
+
+    /// Get the parent of node, or `None` for the root.
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].parent
+    }
+
+    /// Get the children of node. The first child is the main line.
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.nodes[id.0].children
+    }
+
+    /// Return true if node is on the main line, otherwise false.
+    pub fn is_main_line(&self, id: NodeId) -> bool {
+        let mut id = id;
+        while let Some(parent) = self.parent(id) {
+            if self.children(parent)[0] != id {
+                return false;
+            }
+            id = parent;
+        }
+        true
+    }
+
+    /// Return the nodes of the main line, from the first step to the last.
+    pub fn main_line(&self) -> Vec<NodeId> {
+        let mut line = vec![];
+        let mut id = self.root();
+
+        while let Some(&child) = self.children(id).first() {
+            line.push(child);
+            id = child;
+        }
+
+        line
+    }
+
+    /// Return the points of the steps from the root to node.
+    pub fn steps_to(&self, id: NodeId) -> Vec<Point> {
+        let mut steps = vec![];
+        let mut id = id;
+
+        while let Some(point) = self.point(id) {
+            steps.push(point);
+            // Only the root has no point
+            id = self.parent(id).unwrap();
+        }
+
+        steps.reverse();
+        steps
+    }
+
+    /// Create the game at the position of node.
+    pub fn game_at(&self, id: NodeId) -> Game {
+        let mut game = self.start.clone();
+        for point in self.steps_to(id) {
+            // Steps in the tree are validated on the same starting position when added
+            if game.add_step(point).is_err() {
+                break;
+            }
+        }
+        game
+    }
+
+    /// Add a step at point after the current node, and make it the current node.
+    /// If the step already follows the current node, move to it instead.
+    ///
+    /// A new step becomes the main line if the current node has no children,
+    /// otherwise it is added as a variation.
+    /// If the step is invalid, return a result with error of type `GameStepError`.
+    pub fn add_step(&mut self, point: Point) -> Result<NodeId, GameStepError> {
+        self.game.add_step(point)?;
+
+        let id = self.add_child(point);
+        self.current = id;
+        Ok(id)
+    }
+
+    /// Add a variation with the given steps after node, without changing the current node.
+    /// Return the last node of the variation, or an error if any of the steps is invalid,
+    /// in which case the tree is not changed.
+    pub fn add_variation(&mut self, id: NodeId, points: &[Point]) -> Result<NodeId, GameStepError> {
+        // Validate all the steps before changing the tree
+        let mut game = self.game_at(id);
+        for point in points {
+            game.add_step(*point)?;
+        }
+
+        let current = self.current;
+        self.go_to(id);
+
+        let mut last = id;
+        for point in points {
+            last = self.add_step(*point)?;
+        }
+
+        self.go_to(current);
+        Ok(last)
+    }
+
+    /// Promote node and all its ancestors to be the first child of their parents,
+    /// making node part of the main line.
+    pub fn promote(&mut self, id: NodeId) {
+        let mut id = id;
+        while let Some(parent) = self.parent(id) {
+            let children = &mut self.nodes[parent.0].children;
+            let index = children.iter().position(|&child| child == id).unwrap();
+            let child = children.remove(index);
+            children.insert(0, child);
+
+            id = parent;
+        }
+    }
+
+    /// Make node the current node.
+    pub fn go_to(&mut self, id: NodeId) {
+        if self.current != id {
+            self.current = id;
+            self.game = self.game_at(id);
+        }
+    }
+
+    /// Move to the parent of the current node. Return false if the current node is the root.
+    pub fn back(&mut self) -> bool {
+        match self.parent(self.current) {
+            Some(parent) => {
+                self.go_to(parent);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Move to the main line child of the current node. Return false if there are no children.
+    pub fn forward(&mut self) -> bool {
+        match self.children(self.current).first() {
+            Some(&child) => {
+                self.go_to(child);
+                true
+            },
+            None => false,
+        }
+    }
+}
+
+// Private methods
+impl GameTree {
+    /// Create a tree containing only start as the starting position.
+    fn from_start(start: Game) -> GameTree {
+        let root = TreeNode {
+            point: None,
+            parent: None,
+            children: vec![],
+        };
+
+        GameTree {
+            game: start.clone(),
+            start,
+            nodes: vec![root],
+            current: NodeId(0),
+        }
+    }
+
+    /// Get the child of the current node with the step at point, adding it if there is none.
+    /// The step is expected to be valid.
+    fn add_child(&mut self, point: Point) -> NodeId {
+        let existing = self.children(self.current).iter()
+            .find(|&&child| self.point(child) == Some(point))
+            .copied();
+
+        match existing {
+            Some(id) => id,
+            None => {
+                let id = NodeId(self.nodes.len());
+                self.nodes.push(TreeNode {
+                    point: Some(point),
+                    parent: Some(self.current),
+                    children: vec![],
+                });
+                self.nodes[self.current.0].children.push(id);
+                id
+            },
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+
+use crate::{GameSide, GameSpot, GameStepError};
+
+/// The directions of lines through a point on an unbounded board, as steps in x and y.
+static DIRECTIONS: [(i64, i64); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+/// The number of consecutive pieces needed to win when no other value is given.
+static DEFAULT_CONNECT: usize = 5;
+
+/// A point on an unbounded board, which may be anywhere on either side of the origin.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct SignedPoint {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl SignedPoint {
+    pub fn new(x: i64, y: i64) -> SignedPoint {
+        SignedPoint { x, y }
+    }
+
+    /// Return the point at distance steps from this point in direction,
+    /// or `None` if it is beyond the range of the coordinates.
+    fn offset(&self, direction: (i64, i64), distance: i64) -> Option<SignedPoint> {
+        let x = self.x.checked_add(direction.0.checked_mul(distance)?)?;
+        let y = self.y.checked_add(direction.1.checked_mul(distance)?)?;
+        Some(SignedPoint::new(x, y))
+    }
+}
+
+/// The state of a game on an unbounded board, which can never be full.
+#[derive(PartialEq, Clone, Debug)]
+pub enum UnboundedState {
+    /// The state representing that the game is still ongoing.
+    Normal,
+
+    /// The state representing that the game has finished with a winner.
+    Finished {
+        /// The side that won the game.
+        winner_side: GameSide,
+        /// Every winning row through the winning step, holding all its consecutive points.
+        lines: Vec<Vec<SignedPoint>>,
+    },
+}
+
+/// A freestyle game between black and white on a board with no edges.
+///
+/// Only the taken points are stored, and a win is detected on the lines through each
+/// new step, so the cost of a step does not depend on how far apart the pieces are.
+#[derive(Clone, Debug)]
+pub struct UnboundedGame {
+    /// The sides of the pieces on the board, by their points.
+    pieces: HashMap<SignedPoint, GameSide>,
+    /// The points of all the steps taken in the game, with order from earliest to latest.
+    steps: Vec<SignedPoint>,
+    /// The side that plays the next step.
+    side: GameSide,
+    /// The state of the game.
+    state: UnboundedState,
+    /// The number of steps taken when the game was won, if it was.
+    finished_at: Option<usize>,
+    /// The number of consecutive pieces needed to win.
+    connect: usize,
+}
+
+// Initializers
+impl UnboundedGame {
+    /// Create a new game, where five in a row wins.
+    pub fn new() -> UnboundedGame {
+        UnboundedGame::with_connect(DEFAULT_CONNECT)
+    }
+
+    /// Create a new game, where connect pieces in a row win.
+    pub fn with_connect(connect: usize) -> UnboundedGame {
+        UnboundedGame {
+            pieces: HashMap::new(),
+            steps: vec![],
+            side: GameSide::Black,
+            state: UnboundedState::Normal,
+            finished_at: None,
+            connect: connect.max(1),
+        }
+    }
+
+    /// Create a game from given steps, where five in a row wins.
+    pub fn from_steps(steps: &[SignedPoint]) -> Result<UnboundedGame, GameStepError> {
+        let mut game = UnboundedGame::new();
+
+        for point in steps {
+            // Try to add each step. Return early if there is an error
+            game.add_step(*point)?;
+        }
+
+        Ok(game)
+    }
+}
+
+impl Default for UnboundedGame {
+    fn default() -> Self {
+        UnboundedGame::new()
+    }
+}
+
+// Public methods
+impl UnboundedGame {
+    /// Get the number of consecutive pieces needed to win.
+    pub fn connect(&self) -> usize {
+        self.connect
+    }
+
+    /// Get the current state of the game, which was computed when added last step.
+    pub fn state(&self) -> &UnboundedState {
+        &self.state
+    }
+
+    /// Get the side that plays the next step.
+    pub fn side(&self) -> GameSide {
+        self.side
+    }
+
+    /// Get the number of steps taken in the game.
+    pub fn step_count(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Get the point of the last step taken in the game, if any.
+    pub fn last_step(&self) -> Option<SignedPoint> {
+        self.steps.last().copied()
+    }
+
+    /// Get the spot at a point on the board.
+    pub fn spot(&self, point: &SignedPoint) -> GameSpot {
+        self.pieces.get(point).map_or(GameSpot::Empty, |side| GameSpot::Taken(*side))
+    }
+
+    /// Return an iterator that iterates over all steps of this game and their side from the start.
+    pub fn iter_steps(&self) -> impl Iterator<Item=(GameSide, &SignedPoint)> {
+        [GameSide::Black, GameSide::White].iter()
+            .copied()
+            .cycle()
+            .zip(self.steps.iter())
+    }
+
+    /// Get the smallest and largest corners of the rectangle holding every piece,
+    /// or `None` if there are no pieces.
+    pub fn bounds(&self) -> Option<(SignedPoint, SignedPoint)> {
+        let first = *self.steps.first()?;
+
+        Some(self.steps.iter().fold((first, first), |(min, max), p| {
+            (SignedPoint::new(min.x.min(p.x), min.y.min(p.y)), SignedPoint::new(max.x.max(p.x), max.y.max(p.y)))
+        }))
+    }
+
+    /// Validate whether can add step at point.
+    /// Return a result with error of type `GameStepError` if the step is invalid.
+    pub fn validate_step(&self, point: &SignedPoint) -> Result<(), GameStepError> {
+        if self.pieces.contains_key(point) {
+            Err(GameStepError::PointTaken)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Add a step to the game.
+    /// If the step is invalid, do not add the step and
+    /// return a result with error of type `GameStepError`.
+    pub fn add_step(&mut self, point: SignedPoint) -> Result<(), GameStepError> {
+        self.validate_step(&point)?;
+
+        self.pieces.insert(point, self.side);
+        self.steps.push(point);
+
+        if self.state == UnboundedState::Normal {
+            let lines = self.winning_lines(&point, self.side);
+            if !lines.is_empty() {
+                self.state = UnboundedState::Finished {
+                    winner_side: self.side,
+                    lines,
+                };
+                self.finished_at = Some(self.steps.len());
+            }
+        }
+        self.side = self.side.toggle();
+
+        Ok(())
+    }
+
+    /// Remove the last step from the game.
+    /// Return the point of the removed step, or `None` if there are no steps.
+    pub fn undo_step(&mut self) -> Option<SignedPoint> {
+        let point = self.steps.pop()?;
+
+        self.pieces.remove(&point);
+        self.side = self.side.toggle();
+
+        // Only the step that won can change the state, as steps after it do not
+        if self.finished_at.is_some_and(|count| count > self.steps.len()) {
+            self.state = UnboundedState::Normal;
+            self.finished_at = None;
+        }
+
+        Some(point)
+    }
+}
+
+// Private methods
+impl UnboundedGame {
+    /// Return every row of side through point that is long enough to win.
+    fn winning_lines(&self, point: &SignedPoint, side: GameSide) -> Vec<Vec<SignedPoint>> {
+        let connect = self.connect as i64;
+        let own = |p: &Option<SignedPoint>| p.is_some_and(|p| self.pieces.get(&p) == Some(&side));
+
+        DIRECTIONS.iter()
+            .filter_map(|&direction| {
+                // Walk back to the start of the row, then collect it forwards
+                let start = (1..)
+                    .map(|distance| point.offset(direction, -distance))
+                    .take_while(own)
+                    .count() as i64;
+                let line: Vec<SignedPoint> = (-start..)
+                    .map(|distance| point.offset(direction, distance))
+                    .take_while(own)
+                    .flatten()
+                    .collect();
+
+                if line.len() as i64 >= connect {
+                    Some(line)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}